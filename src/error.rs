@@ -4,4 +4,22 @@
 pub enum Error {
   #[error("Generic {0}")]
   Generic(String),
+  // Students, by id, with the compulsory subjects they could not be placed in
+  #[error("Compulsory subjects could not be placed for {}", student_list(.0))]
+  CompulsoryUnmet(Vec<(String, Vec<String>)>),
+  // A saved school names something, such as a subject, that the file does not contain
+  #[error("{owner} refers to {kind} ({name}), which does not exist")]
+  DanglingReference {
+    owner: String,
+    kind: String,
+    name: String,
+  },
+}
+
+fn student_list(unmet_list: &[(String, Vec<String>)]) -> String {
+  unmet_list
+    .iter()
+    .map(|(student_id, subject_list)| format!("{} ({})", student_id, subject_list.join(", ")))
+    .collect::<Vec<_>>()
+    .join(", ")
 }
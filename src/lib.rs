@@ -1,7 +1,9 @@
 pub mod error;
 pub mod prelude;
 pub mod schedule;
+pub mod solver;
 pub mod timetable;
 
 pub use crate::schedule::*;
+pub use crate::solver::*;
 pub use crate::timetable::*;
@@ -3,61 +3,48 @@
 extern crate schedule;
 
 use schedule::prelude::*;
-use schedule::{random, Schedule};
+use schedule::{random, AnnealConfig, PeriodGrid, Schedule};
+use std::time::Duration;
 
 fn main() -> Result<()> {
-  let mut high_school = random::random_schedule(2000, 30, 140, 20);
-
-  high_school.sort();
-
-  high_school
-    .timetable
-    .slot_list
-    .iter()
-    .enumerate()
-    .for_each(|(i, x)| {
-      println!("Slot: {}", i);
-      x.lock().unwrap().class_list.iter().for_each(|x| {
-        if !x.removed {
-          println!(
-            "  Class:\n    Subject: {}\n    Department: {}\n    Students:",
-            x.subject.upgrade().unwrap().name,
-            x.department.upgrade().unwrap().name,
-          );
-          x.student_list.iter().for_each(|student| {
-            let student = student.upgrade().unwrap();
-            println!(
-              "      {} {} (id={})",
-              student.first_name, student.last_name, student.id
-            );
-          });
-        }
-        println!("");
-      })
-    });
-
-  // Get timetable for person four
-  println!("\nPerson Four's Schedule:\n");
-  high_school
-    .timetable
-    .slot_list
-    .iter()
-    .enumerate()
-    .for_each(|(i, x)| {
-      x.lock().unwrap().class_list.iter().for_each(|x| {
-        x.student_list.iter().for_each(|student| {
-          let student = student.upgrade().unwrap();
-          if student.id == "4".to_string() {
-            println!(
-              "Slot {}: {} in the {} department",
-              i,
-              x.subject.upgrade().unwrap().name,
-              x.department.upgrade().unwrap().name
-            );
-          }
-        });
-      })
-    });
+  // Pass the seed printed by an earlier run to replay it exactly
+  let mut high_school = match std::env::args().nth(1) {
+    Some(seed) => random::random_schedule_from_seed(
+      seed
+        .parse()
+        .map_err(|_| Error::Generic(format!("Seed ({}) is not a number", seed)))?,
+      2000,
+      30,
+      140,
+      20,
+    ),
+    None => random::random_schedule(2000, 30, 140, 20),
+  };
+  high_school.anneal_config = Some(AnnealConfig {
+    time_limit: Duration::from_secs(5),
+    ..Default::default()
+  });
+
+  let report = high_school.sort()?;
+
+  // Class lists and every student's timetable, for staff to browse
+  high_school.write_html_site("report")?;
+  println!("Timetable written to report/index.html");
+
+  // Five slots meeting every day, around a lunch break
+  high_school.set_period_grid(
+    PeriodGrid {
+      break_list: vec![3],
+      ..PeriodGrid::new(vec!["Mon", "Tue", "Wed", "Thu", "Fri"], 6)
+    },
+    5,
+  )?;
+  if let Some(week) = high_school.student_week("4") {
+    println!("\n{}", week);
+  }
+
+  println!("\nSeed: {}", high_school.seed.unwrap());
+  println!("{}", report);
 
   Ok(())
 }
@@ -0,0 +1,191 @@
+use crate::{prelude::*, Subject};
+use std::sync::Weak;
+
+#[derive(Default)]
+pub struct CohortBuilder {
+  name: Option<String>,
+  subject_list: Vec<Weak<Subject>>,
+  slot_list: Vec<usize>,
+}
+
+// A year group, such as "Year 12", with its own subject offerings and slots. Subjects offered
+// to several cohorts can be taken together in the same class.
+#[derive(Debug)]
+pub struct Cohort {
+  pub name: String,
+  // Subjects the cohort's students can choose from
+  pub subject_list: Vec<Weak<Subject>>,
+  // Slots of the timetable the cohort's students are taught in, in order
+  pub slot_list: Vec<usize>,
+}
+
+impl Cohort {
+  pub fn offers(&self, subject: &Weak<Subject>) -> bool {
+    self.subject_list.iter().any(|x| x.ptr_eq(subject))
+  }
+  pub fn uses_slot(&self, slot: usize) -> bool {
+    self.slot_list.contains(&slot)
+  }
+}
+
+impl CohortBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn name(self, new_name: impl Into<String>) -> Self {
+    Self {
+      name: Some(new_name.into()),
+      ..self
+    }
+  }
+  pub fn subject(self, new_subject: Weak<Subject>) -> Self {
+    let mut new_subject_list = self.subject_list;
+    new_subject_list.push(new_subject);
+    Self {
+      subject_list: new_subject_list,
+      ..self
+    }
+  }
+  pub fn slot(self, new_slot: usize) -> Self {
+    let mut new_slot_list = self.slot_list;
+    if !new_slot_list.contains(&new_slot) {
+      new_slot_list.push(new_slot);
+      new_slot_list.sort();
+    }
+    Self {
+      slot_list: new_slot_list,
+      ..self
+    }
+  }
+  pub fn build(self) -> Result<Cohort> {
+    let Some(name) = self.name else {
+      return Err(Error::Generic(
+        "Cannot build cohort without name".to_string(),
+      ));
+    };
+    if self.slot_list.is_empty() {
+      return Err(Error::Generic(
+        "Cannot build cohort without any slots".to_string(),
+      ));
+    }
+    Ok(Cohort {
+      name,
+      subject_list: self.subject_list,
+      slot_list: self.slot_list,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Schedule;
+  use std::sync::Arc;
+
+  fn school() -> Schedule {
+    let mut high_school = Schedule::with_slot_count(4);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school.new_department("Languages", 1, 30, 1).unwrap();
+    high_school.new_subject("Algebra", vec!["Maths"]).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Latin", vec!["Languages"]).unwrap();
+    high_school
+      .new_cohort("Year 9", vec!["Algebra"], vec![0, 1])
+      .unwrap();
+    high_school
+      .new_cohort("Year 12", vec!["Calculus", "Latin"], vec![1, 2, 3])
+      .unwrap();
+    high_school
+      .new_cohort("Year 13", vec!["Latin"], vec![3])
+      .unwrap();
+    high_school
+  }
+
+  #[test]
+  #[should_panic]
+  fn create_cohort_missing_name() {
+    let _cohort = CohortBuilder::new().slot(0).build().unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn create_cohort_without_slots() {
+    let _cohort = CohortBuilder::new().name("Year 12").build().unwrap();
+  }
+
+  #[test]
+  fn create_cohort() {
+    let cohort = CohortBuilder::new()
+      .name("Year 12")
+      .slot(3)
+      .slot(1)
+      .slot(3)
+      .build()
+      .unwrap();
+    assert_eq!(cohort.slot_list, vec![1, 3]);
+    assert!(cohort.uses_slot(1));
+    assert!(!cohort.uses_slot(0));
+  }
+
+  #[test]
+  fn test_sort_cohorts() {
+    let mut high_school = school();
+    for id in ["1", "2"] {
+      high_school
+        .new_student_in_cohort("Person", id, id, "Year 9", vec!["Algebra"])
+        .unwrap();
+    }
+    high_school
+      .new_student_in_cohort("Person", "3", "3", "Year 13", vec!["Latin"])
+      .unwrap();
+    high_school
+      .new_student_in_cohort("Person", "4", "4", "Year 12", vec!["Calculus", "Latin"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+    assert_eq!(report.placed_count(), report.requested_count());
+
+    // Every student is only taught in their cohort's slots
+    for student in &high_school.student_list {
+      for (slot_index, slot) in high_school.timetable.slot_list.iter().enumerate() {
+        if slot
+          .lock()
+          .unwrap()
+          .contains_student(Arc::downgrade(student))
+        {
+          assert!(student.uses_slot(slot_index));
+        }
+      }
+    }
+    // Year 12 and 13 share the one Latin class in the only slot they have in common
+    let slot = high_school.timetable.slot_list[3].lock().unwrap();
+    let latin = slot
+      .class_list
+      .iter()
+      .find(|x| x.subject.upgrade().unwrap().name == "Latin")
+      .unwrap();
+    assert_eq!(latin.student_list.len(), 2);
+    drop(slot);
+
+    let cohort_report = high_school.cohort_report("Year 9").unwrap();
+    assert_eq!(cohort_report.student_report_list.len(), 2);
+    assert_eq!(cohort_report.classes_created, 1);
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_subject_not_offered_to_cohort() {
+    let mut high_school = school();
+    high_school
+      .new_student_in_cohort("Person", "1", "1", "Year 9", vec!["Latin"])
+      .unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_cohort_slot_out_of_range() {
+    let mut high_school = school();
+    high_school
+      .new_cohort("Year 10", vec!["Algebra"], vec![4])
+      .unwrap();
+  }
+}
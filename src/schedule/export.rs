@@ -0,0 +1,157 @@
+//! Exporting class lists and student timetables as CSV, for printing and for uploading back
+//! into a student management system
+
+use super::{Schedule, StudentReport};
+use crate::prelude::*;
+use std::{io::Write, sync::Arc};
+
+// Separates several values within one cell
+const CELL_SEPARATOR: &str = "; ";
+
+fn write_error(e: csv::Error) -> Error {
+  Error::Generic(format!("Cannot write CSV: {}", e))
+}
+
+impl Schedule {
+  // One row per class, removed classes included: slot, subject, department, class size,
+  // removed status and the ids of its students
+  pub fn export_class_list_csv(&self, writer: impl Write) -> Result<()> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer
+      .write_record([
+        "Slot",
+        "Subject",
+        "Department",
+        "Size",
+        "Removed",
+        "Students",
+      ])
+      .map_err(write_error)?;
+    for (slot_index, slot) in self.timetable.slot_list.iter().enumerate() {
+      for class in &slot.lock().unwrap().class_list {
+        let id_list: Vec<String> = class
+          .student_list
+          .iter()
+          .map(|x| x.upgrade().unwrap().id.clone())
+          .collect();
+        csv_writer
+          .write_record([
+            slot_index.to_string(),
+            class.subject.upgrade().unwrap().name.clone(),
+            class.department.upgrade().unwrap().name.clone(),
+            class.student_list.len().to_string(),
+            class.removed.to_string(),
+            id_list.join(CELL_SEPARATOR),
+          ])
+          .map_err(write_error)?;
+      }
+    }
+    csv_writer
+      .flush()
+      .map_err(|e| Error::Generic(format!("Cannot write CSV: {}", e)))
+  }
+
+  // One row per student: id, name, the subject and department of their class in each slot,
+  // left blank when they are free, and the requested subjects they were not placed in
+  pub fn export_student_timetable_csv(&self, writer: impl Write) -> Result<()> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    let mut header = vec![
+      "Id".to_string(),
+      "First name".to_string(),
+      "Last name".to_string(),
+    ];
+    for slot_index in 0..self.slot_count() {
+      header.push(format!("Slot {} subject", slot_index));
+      header.push(format!("Slot {} department", slot_index));
+    }
+    header.push("Unplaced".to_string());
+    csv_writer.write_record(&header).map_err(write_error)?;
+
+    for student in &self.student_list {
+      let mut row = vec![
+        student.id.clone(),
+        student.first_name.clone(),
+        student.last_name.clone(),
+      ];
+      for slot in &self.timetable.slot_list {
+        let slot = slot.lock().unwrap();
+        match slot
+          .class_list
+          .iter()
+          .find(|x| !x.removed && x.contains(Arc::downgrade(student)))
+        {
+          Some(class) => {
+            row.push(class.subject.upgrade().unwrap().name.clone());
+            row.push(class.department.upgrade().unwrap().name.clone());
+          }
+          None => row.extend([String::new(), String::new()]),
+        }
+      }
+      let report = StudentReport::new(student, &self.timetable);
+      let unplaced_list: Vec<String> = report
+        .dropped
+        .iter()
+        .chain(&report.unplaced)
+        .map(|x| x.upgrade().unwrap().name.clone())
+        .collect();
+      row.push(unplaced_list.join(CELL_SEPARATOR));
+      csv_writer.write_record(&row).map_err(write_error)?;
+    }
+    csv_writer
+      .flush()
+      .map_err(|e| Error::Generic(format!("Cannot write CSV: {}", e)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sorted_school() -> Schedule {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 2, 30, 1).unwrap();
+    high_school.new_department("Science", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Physics"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Physics", "Calculus"])
+      .unwrap();
+    high_school.sort().unwrap();
+    high_school
+  }
+
+  #[test]
+  fn test_export_class_list() {
+    let high_school = sorted_school();
+    let mut output = Vec::new();
+    high_school.export_class_list_csv(&mut output).unwrap();
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      "\
+Slot,Subject,Department,Size,Removed,Students
+0,Physics,Science,2,false,1; 2
+1,Calculus,Maths,1,true,2
+"
+    );
+  }
+
+  #[test]
+  fn test_export_student_timetable() {
+    let high_school = sorted_school();
+    let mut output = Vec::new();
+    high_school
+      .export_student_timetable_csv(&mut output)
+      .unwrap();
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      "\
+Id,First name,Last name,Slot 0 subject,Slot 0 department,Slot 1 subject,Slot 1 department,Unplaced
+1,Person,One,Physics,Science,,,
+2,Person,Two,Physics,Science,,,Calculus
+"
+    );
+  }
+}
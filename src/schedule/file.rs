@@ -0,0 +1,689 @@
+//! Saving and loading a whole school, with its timetable, as JSON or TOML. Entities refer to
+//! each other by name, or by id for students and teachers, and are linked up again on load.
+
+use super::{
+  CohortBuilder, Department, RoomBuilder, Schedule, StudentBuilder, Subject, SubjectBuilder,
+  TeacherBuilder,
+};
+use crate::{prelude::*, BlockingPlan, Class, ObjectiveWeights, PeriodMap};
+use serde::{Deserialize, Serialize};
+use std::{
+  path::Path,
+  sync::{Arc, Weak},
+};
+
+// Version written to new files. Files from a newer version are refused rather than misread.
+pub const FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchoolFile {
+  pub version: u32,
+  pub slot_count: usize,
+  // Written as the signed integer with the same bits, as TOML cannot hold seeds above i64::MAX
+  #[serde(default, with = "seed_bits")]
+  pub seed: Option<u64>,
+  #[serde(default)]
+  pub objective_weights: ObjectiveWeights,
+  #[serde(default)]
+  pub department_list: Vec<Department>,
+  #[serde(default)]
+  pub subject_list: Vec<SubjectFile>,
+  #[serde(default)]
+  pub cohort_list: Vec<CohortFile>,
+  #[serde(default)]
+  pub student_list: Vec<StudentFile>,
+  #[serde(default)]
+  pub teacher_list: Vec<TeacherFile>,
+  #[serde(default)]
+  pub room_list: Vec<RoomFile>,
+  pub period_map: Option<PeriodMap>,
+  // One block per slot
+  pub blocking_plan: Option<Vec<BlockFile>>,
+  // One entry per slot, or none for a school that has not been sorted
+  #[serde(default)]
+  pub timetable: Vec<SlotFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubjectFile {
+  pub name: String,
+  pub department_list: Vec<String>,
+  #[serde(default)]
+  pub feature_list: Vec<String>,
+  #[serde(default = "default_slot_demand")]
+  pub slot_demand: usize,
+  #[serde(default)]
+  pub compulsory: bool,
+  #[serde(default)]
+  pub prerequisite_list: Vec<String>,
+  // Subjects earlier in the file's subject list
+  #[serde(default)]
+  pub corequisite_list: Vec<String>,
+  #[serde(default)]
+  pub exclusion_list: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CohortFile {
+  pub name: String,
+  pub subject_list: Vec<String>,
+  pub slot_list: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StudentFile {
+  pub id: String,
+  pub first_name: String,
+  pub last_name: String,
+  // Choices in order of preference
+  pub subject_list: Vec<String>,
+  #[serde(default)]
+  pub reserve_list: Vec<String>,
+  #[serde(default)]
+  pub compulsory_list: Vec<String>,
+  #[serde(default)]
+  pub completed_list: Vec<String>,
+  pub cohort: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeacherFile {
+  pub id: String,
+  pub name: String,
+  #[serde(default)]
+  pub subject_list: Vec<String>,
+  #[serde(default)]
+  pub department_list: Vec<String>,
+  pub max_class_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomFile {
+  pub name: String,
+  pub capacity: usize,
+  pub department_list: Vec<String>,
+  #[serde(default)]
+  pub feature_list: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockFile {
+  pub entry_list: Vec<BlockEntryFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockEntryFile {
+  pub subject: String,
+  pub department: String,
+  pub class_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlotFile {
+  pub class_list: Vec<ClassFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassFile {
+  pub subject: String,
+  pub department: String,
+  // Ids of the students in the class
+  pub student_list: Vec<String>,
+  #[serde(default)]
+  pub removed: bool,
+  pub teacher: Option<String>,
+  pub room: Option<String>,
+  pub group: Option<usize>,
+}
+
+// Read before the rest of a file, so that newer files are refused with a clear error
+#[derive(Deserialize)]
+struct FileHeader {
+  version: u32,
+}
+
+fn default_slot_demand() -> usize {
+  1
+}
+
+mod seed_bits {
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+  // Either form is read back, so JSON files written with the plain seed still load
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Seed {
+    Signed(i64),
+    Unsigned(u64),
+  }
+
+  pub fn serialize<S: Serializer>(seed: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    seed.map(|x| x as i64).serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Ok(Option::<Seed>::deserialize(deserializer)?.map(|x| match x {
+      Seed::Signed(seed) => seed as u64,
+      Seed::Unsigned(seed) => seed,
+    }))
+  }
+}
+
+// Names of the linked entities, in order
+fn name_list<T>(list: &[Weak<T>], name: impl Fn(&T) -> &str) -> Vec<String> {
+  list
+    .iter()
+    .map(|x| name(&x.upgrade().unwrap()).to_string())
+    .collect()
+}
+
+// Links up a name read from a file, or reports what referred to it
+fn resolve<T>(found: Option<Arc<T>>, owner: &str, kind: &str, name: &str) -> Result<Weak<T>> {
+  match found {
+    Some(element) => Ok(Arc::downgrade(&element)),
+    None => Err(Error::DanglingReference {
+      owner: owner.to_string(),
+      kind: kind.to_string(),
+      name: name.to_string(),
+    }),
+  }
+}
+
+fn check_version(version: u32) -> Result<()> {
+  if version > FILE_VERSION {
+    return Err(Error::Generic(format!(
+      "School file version {} is newer than the supported version {}",
+      version, FILE_VERSION
+    )));
+  }
+  Ok(())
+}
+
+impl Schedule {
+  pub fn to_file(&self) -> SchoolFile {
+    let subject_names = |list: &[Weak<Subject>]| name_list(list, |x| &x.name);
+    let department_names = |list: &[Weak<Department>]| name_list(list, |x| &x.name);
+    SchoolFile {
+      version: FILE_VERSION,
+      slot_count: self.slot_count(),
+      seed: self.seed,
+      objective_weights: self.objective_weights.clone(),
+      department_list: self
+        .department_list
+        .iter()
+        .map(|x| Department::clone(x))
+        .collect(),
+      subject_list: self
+        .subject_list
+        .iter()
+        .map(|subject| SubjectFile {
+          name: subject.name.clone(),
+          department_list: department_names(&subject.department_list),
+          feature_list: subject.feature_list.clone(),
+          slot_demand: subject.slot_demand,
+          compulsory: subject.compulsory,
+          prerequisite_list: subject.prerequisite_list.clone(),
+          corequisite_list: subject_names(&subject.corequisite_list),
+          exclusion_list: subject_names(&subject.exclusion_list),
+        })
+        .collect(),
+      cohort_list: self
+        .cohort_list
+        .iter()
+        .map(|cohort| CohortFile {
+          name: cohort.name.clone(),
+          subject_list: subject_names(&cohort.subject_list),
+          slot_list: cohort.slot_list.clone(),
+        })
+        .collect(),
+      student_list: self
+        .student_list
+        .iter()
+        .map(|student| StudentFile {
+          id: student.id.clone(),
+          first_name: student.first_name.clone(),
+          last_name: student.last_name.clone(),
+          subject_list: subject_names(&student.subject_list),
+          reserve_list: subject_names(&student.reserve_list),
+          compulsory_list: subject_names(&student.compulsory_list),
+          completed_list: student.completed_list.clone(),
+          cohort: student
+            .cohort
+            .as_ref()
+            .map(|x| x.upgrade().unwrap().name.clone()),
+        })
+        .collect(),
+      teacher_list: self
+        .teacher_list
+        .iter()
+        .map(|teacher| TeacherFile {
+          id: teacher.id.clone(),
+          name: teacher.name.clone(),
+          subject_list: subject_names(&teacher.subject_list),
+          department_list: department_names(&teacher.department_list),
+          max_class_count: teacher.max_class_count,
+        })
+        .collect(),
+      room_list: self
+        .room_list
+        .iter()
+        .map(|room| RoomFile {
+          name: room.name.clone(),
+          capacity: room.capacity,
+          department_list: department_names(&room.department_list),
+          feature_list: room.feature_list.clone(),
+        })
+        .collect(),
+      period_map: self.period_map.clone(),
+      blocking_plan: self.blocking_plan.as_ref().map(|plan| {
+        plan
+          .block_list
+          .iter()
+          .map(|block| BlockFile {
+            entry_list: block
+              .iter()
+              .map(|entry| BlockEntryFile {
+                subject: entry.subject.upgrade().unwrap().name.clone(),
+                department: entry.department.upgrade().unwrap().name.clone(),
+                class_count: entry.class_count,
+              })
+              .collect(),
+          })
+          .collect()
+      }),
+      timetable: self
+        .timetable
+        .slot_list
+        .iter()
+        .map(|slot| SlotFile {
+          class_list: slot
+            .lock()
+            .unwrap()
+            .class_list
+            .iter()
+            .map(|class| ClassFile {
+              subject: class.subject.upgrade().unwrap().name.clone(),
+              department: class.department.upgrade().unwrap().name.clone(),
+              student_list: name_list(&class.student_list, |x| &x.id),
+              removed: class.removed,
+              teacher: class
+                .teacher
+                .as_ref()
+                .map(|x| x.upgrade().unwrap().id.clone()),
+              room: class
+                .room
+                .as_ref()
+                .map(|x| x.upgrade().unwrap().name.clone()),
+              group: class.group,
+            })
+            .collect(),
+        })
+        .collect(),
+    }
+  }
+
+  // Rebuilds a school from a file, checking it the same way as a school built in code
+  pub fn from_file(file: SchoolFile) -> Result<Schedule> {
+    check_version(file.version)?;
+    let mut schedule = Schedule {
+      objective_weights: file.objective_weights,
+      seed: file.seed,
+      ..Schedule::with_slot_count(file.slot_count)
+    };
+    for department in file.department_list {
+      schedule.add_department(department)?;
+    }
+    for subject in file.subject_list {
+      let owner = format!("Subject ({})", subject.name);
+      let mut subject_builder = SubjectBuilder::new()
+        .name(&subject.name)
+        .slot_demand(subject.slot_demand)
+        .compulsory(subject.compulsory);
+      for name in &subject.department_list {
+        let department = resolve(schedule.get_department(name), &owner, "department", name)?;
+        subject_builder = subject_builder.department(department);
+      }
+      for feature in subject.feature_list {
+        subject_builder = subject_builder.feature(feature);
+      }
+      for prerequisite in subject.prerequisite_list {
+        subject_builder = subject_builder.prerequisite(prerequisite);
+      }
+      for name in &subject.corequisite_list {
+        let corequisite = resolve(schedule.get_subject(name), &owner, "corequisite", name)?;
+        subject_builder = subject_builder.corequisite(corequisite);
+      }
+      for name in &subject.exclusion_list {
+        let exclusion = resolve(schedule.get_subject(name), &owner, "exclusion", name)?;
+        subject_builder = subject_builder.exclusion(exclusion);
+      }
+      schedule.add_subject(subject_builder.build()?)?;
+    }
+    for cohort in file.cohort_list {
+      let owner = format!("Cohort ({})", cohort.name);
+      let mut cohort_builder = CohortBuilder::new().name(&cohort.name);
+      for name in &cohort.subject_list {
+        let subject = resolve(schedule.get_subject(name), &owner, "subject", name)?;
+        cohort_builder = cohort_builder.subject(subject);
+      }
+      for slot in cohort.slot_list {
+        cohort_builder = cohort_builder.slot(slot);
+      }
+      schedule.add_cohort(cohort_builder.build()?)?;
+    }
+    for student in file.student_list {
+      let owner = format!("Student (id: {})", student.id);
+      let mut student_builder = StudentBuilder::new()
+        .first_name(&student.first_name)
+        .last_name(&student.last_name)
+        .id(&student.id);
+      for name in &student.subject_list {
+        let subject = resolve(schedule.get_subject(name), &owner, "subject", name)?;
+        student_builder = student_builder.subject(subject);
+      }
+      for name in &student.reserve_list {
+        let reserve = resolve(schedule.get_subject(name), &owner, "reserve", name)?;
+        student_builder = student_builder.reserve(reserve);
+      }
+      for name in &student.compulsory_list {
+        let subject = resolve(
+          schedule.get_subject(name),
+          &owner,
+          "compulsory subject",
+          name,
+        )?;
+        student_builder = student_builder.compulsory(subject);
+      }
+      for name in student.completed_list {
+        student_builder = student_builder.completed(name);
+      }
+      if let Some(name) = &student.cohort {
+        let cohort = resolve(schedule.get_cohort(name), &owner, "cohort", name)?;
+        student_builder = student_builder.cohort(cohort);
+      }
+      schedule.add_student(student_builder.build()?)?;
+    }
+    for teacher in file.teacher_list {
+      let owner = format!("Teacher (id: {})", teacher.id);
+      let mut teacher_builder = TeacherBuilder::new()
+        .id(&teacher.id)
+        .name(&teacher.name)
+        .max_class_count(teacher.max_class_count);
+      for name in &teacher.subject_list {
+        let subject = resolve(schedule.get_subject(name), &owner, "subject", name)?;
+        teacher_builder = teacher_builder.subject(subject);
+      }
+      for name in &teacher.department_list {
+        let department = resolve(schedule.get_department(name), &owner, "department", name)?;
+        teacher_builder = teacher_builder.department(department);
+      }
+      schedule.add_teacher(teacher_builder.build()?)?;
+    }
+    for room in file.room_list {
+      let owner = format!("Room ({})", room.name);
+      let mut room_builder = RoomBuilder::new().name(&room.name).capacity(room.capacity);
+      for name in &room.department_list {
+        let department = resolve(schedule.get_department(name), &owner, "department", name)?;
+        room_builder = room_builder.department(department);
+      }
+      for feature in room.feature_list {
+        room_builder = room_builder.feature(feature);
+      }
+      schedule.add_room(room_builder.build()?)?;
+    }
+
+    if let Some(period_map) = file.period_map {
+      if period_map.slot_period_list.len() != schedule.slot_count() {
+        return Err(Error::Generic(format!(
+          "Period map has periods for {} slots but there are {} slots",
+          period_map.slot_period_list.len(),
+          schedule.slot_count()
+        )));
+      }
+      schedule.period_map = Some(period_map);
+    }
+    if let Some(block_list) = file.blocking_plan {
+      let mut plan = BlockingPlan::new(block_list.len());
+      for (block_index, block) in block_list.into_iter().enumerate() {
+        let owner = format!("Blocking plan slot {}", block_index);
+        for entry in block.entry_list {
+          let subject = resolve(
+            schedule.get_subject(&entry.subject),
+            &owner,
+            "subject",
+            &entry.subject,
+          )?;
+          let department = resolve(
+            schedule.get_department(&entry.department),
+            &owner,
+            "department",
+            &entry.department,
+          )?;
+          for _ in 0..entry.class_count {
+            plan.add_class(block_index, Weak::clone(&subject), Weak::clone(&department));
+          }
+        }
+      }
+      schedule.set_blocking_plan(plan)?;
+    }
+
+    if file.timetable.is_empty() {
+      schedule.timetable = schedule.empty_timetable();
+      return Ok(schedule);
+    }
+    if file.timetable.len() != schedule.slot_count() {
+      return Err(Error::Generic(format!(
+        "Timetable has {} slots but there are {} slots",
+        file.timetable.len(),
+        schedule.slot_count()
+      )));
+    }
+    let timetable = schedule.empty_timetable();
+    for (slot_index, slot) in file.timetable.into_iter().enumerate() {
+      let mut class_list = Vec::new();
+      for class in slot.class_list {
+        let owner = format!("Class of {} in slot {}", class.subject, slot_index);
+        let subject = resolve(
+          schedule.get_subject(&class.subject),
+          &owner,
+          "subject",
+          &class.subject,
+        )?;
+        let department = resolve(
+          schedule.get_department(&class.department),
+          &owner,
+          "department",
+          &class.department,
+        )?;
+        let mut student_list = Vec::new();
+        for id in &class.student_list {
+          student_list.push(resolve(schedule.get_student(id), &owner, "student", id)?);
+        }
+        let mut new_class = Class::new(subject, department, student_list);
+        new_class.removed = class.removed;
+        new_class.group = class.group;
+        if let Some(id) = &class.teacher {
+          new_class.teacher = Some(resolve(schedule.get_teacher(id), &owner, "teacher", id)?);
+        }
+        if let Some(name) = &class.room {
+          new_class.room = Some(resolve(schedule.get_room(name), &owner, "room", name)?);
+        }
+        class_list.push(new_class);
+      }
+      timetable.slot_list[slot_index].lock().unwrap().class_list = class_list;
+    }
+    schedule.timetable = timetable;
+    Ok(schedule)
+  }
+
+  pub fn to_json(&self) -> Result<String> {
+    serde_json::to_string_pretty(&self.to_file())
+      .map_err(|e| Error::Generic(format!("Cannot write school as JSON: {}", e)))
+  }
+
+  pub fn from_json(text: &str) -> Result<Schedule> {
+    let header: FileHeader = serde_json::from_str(text)
+      .map_err(|e| Error::Generic(format!("Cannot read school file: {}", e)))?;
+    check_version(header.version)?;
+    let file = serde_json::from_str(text)
+      .map_err(|e| Error::Generic(format!("Cannot read school file: {}", e)))?;
+    Schedule::from_file(file)
+  }
+
+  pub fn to_toml(&self) -> Result<String> {
+    toml::to_string(&self.to_file())
+      .map_err(|e| Error::Generic(format!("Cannot write school as TOML: {}", e)))
+  }
+
+  pub fn from_toml(text: &str) -> Result<Schedule> {
+    let header: FileHeader = toml::from_str(text)
+      .map_err(|e| Error::Generic(format!("Cannot read school file: {}", e)))?;
+    check_version(header.version)?;
+    let file = toml::from_str(text)
+      .map_err(|e| Error::Generic(format!("Cannot read school file: {}", e)))?;
+    Schedule::from_file(file)
+  }
+
+  // Writes the school to the path, as TOML when it ends in .toml and as JSON otherwise
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let text = if is_toml(path) {
+      self.to_toml()?
+    } else {
+      self.to_json()?
+    };
+    std::fs::write(path, text)
+      .map_err(|e| Error::Generic(format!("Cannot write {}: {}", path.display(), e)))
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<Schedule> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+      .map_err(|e| Error::Generic(format!("Cannot read {}: {}", path.display(), e)))?;
+    if is_toml(path) {
+      Schedule::from_toml(&text)
+    } else {
+      Schedule::from_json(&text)
+    }
+  }
+}
+
+fn is_toml(path: &Path) -> bool {
+  path.extension().is_some_and(|x| x == "toml")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sorted_school() -> Schedule {
+    let mut high_school = Schedule::with_slot_count(3);
+    high_school.seed = Some(7);
+    high_school.new_department("Maths", 1, 30, 2).unwrap();
+    high_school.new_department("Science", 1, 30, 2).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject_with_features("Chemistry", vec!["Science"], vec!["Lab"])
+      .unwrap();
+    high_school
+      .new_subject_with_rules(
+        "Physics",
+        vec!["Science"],
+        crate::SubjectRules {
+          corequisite_list: vec!["Calculus".to_string()],
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    high_school
+      .new_cohort(
+        "Year 12",
+        vec!["Calculus", "Chemistry", "Physics"],
+        vec![0, 1, 2],
+      )
+      .unwrap();
+    high_school
+      .new_student_in_cohort("Person", "One", "1", "Year 12", vec!["Calculus", "Physics"])
+      .unwrap();
+    high_school
+      .new_student_with_reserves("Person", "Two", "2", vec!["Chemistry"], vec!["Calculus"])
+      .unwrap();
+    high_school
+      .new_teacher("t1", "Teacher One", vec!["Calculus"], vec!["Science"], 3)
+      .unwrap();
+    high_school
+      .new_room("S1", 30, vec!["Science"], vec!["Lab"])
+      .unwrap();
+    high_school
+      .new_room("M1", 30, vec!["Maths"], Vec::<String>::new())
+      .unwrap();
+    high_school
+      .set_period_grid(crate::PeriodGrid::new(vec!["Mon", "Tue", "Wed"], 4), 2)
+      .unwrap();
+    high_school.sort().unwrap();
+    high_school
+  }
+
+  #[test]
+  fn test_json_round_trip() {
+    let high_school = sorted_school();
+    let loaded = Schedule::from_json(&high_school.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.to_file(), high_school.to_file());
+    let student = loaded.get_student("1").unwrap();
+    assert_eq!(
+      student.cohort.as_ref().unwrap().upgrade().unwrap().name,
+      "Year 12"
+    );
+    assert!(loaded.timetable.slot_list.iter().any(|slot| slot
+      .lock()
+      .unwrap()
+      .contains_student(Arc::downgrade(&student))));
+  }
+
+  #[test]
+  fn test_toml_round_trip() {
+    let high_school = sorted_school();
+    let loaded = Schedule::from_toml(&high_school.to_toml().unwrap()).unwrap();
+    assert_eq!(loaded.to_file(), high_school.to_file());
+  }
+
+  #[test]
+  fn test_large_seed_round_trip() {
+    let mut high_school = sorted_school();
+    high_school.seed = Some(u64::MAX - 1);
+    let loaded = Schedule::from_toml(&high_school.to_toml().unwrap()).unwrap();
+    assert_eq!(loaded.seed, Some(u64::MAX - 1));
+    let loaded = Schedule::from_json(&high_school.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.seed, Some(u64::MAX - 1));
+  }
+
+  #[test]
+  fn test_dangling_reference() {
+    let mut file = sorted_school().to_file();
+    file.student_list[1].reserve_list = vec!["Latin".to_string()];
+    let Err(error) = Schedule::from_file(file) else {
+      panic!("school with a missing subject was loaded");
+    };
+    assert_eq!(
+      error.to_string(),
+      "Student (id: 2) refers to reserve (Latin), which does not exist"
+    );
+
+    let mut file = sorted_school().to_file();
+    file.timetable[0].class_list[0]
+      .student_list
+      .push("9".to_string());
+    assert!(matches!(
+      Schedule::from_file(file),
+      Err(Error::DanglingReference { kind, .. }) if kind == "student"
+    ));
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_newer_version() {
+    let mut file = sorted_school().to_file();
+    file.version = FILE_VERSION + 1;
+    Schedule::from_json(&serde_json::to_string(&file).unwrap()).unwrap();
+  }
+}
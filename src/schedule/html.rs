@@ -0,0 +1,363 @@
+//! A static HTML site of a sorted school, for sharing with staff: an overview of every slot,
+//! a page per subject and a page per student
+
+use super::{Schedule, SortReport, StudentReport};
+use crate::{prelude::*, Class, Student, Subject};
+use std::{
+  fmt::Write,
+  path::Path,
+  sync::{Arc, Weak},
+};
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:1em 0}\
+th,td{border:1px solid #bbb;padding:.3em .6em;text-align:left;vertical-align:top}\
+th{background:#eee}\
+.removed{color:#999;text-decoration:line-through}\
+.unplaced{background:#fdd;font-weight:bold}";
+
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&#39;")
+}
+
+// Lowercase letters and digits of the text, with everything else turned into dashes
+fn slug(text: &str) -> String {
+  text
+    .chars()
+    .map(|x| {
+      if x.is_ascii_alphanumeric() {
+        x.to_ascii_lowercase()
+      } else {
+        '-'
+      }
+    })
+    .collect()
+}
+
+fn page(title: &str, root: &str, body: &str) -> String {
+  format!(
+    "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+     <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+     <p><a href=\"{root}index.html\">Overview</a></p>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+    title = escape_html(title),
+  )
+}
+
+impl Schedule {
+  // Site paths are numbered by position as well as named, so no two pages clash
+  fn subject_page(&self, subject: &Weak<Subject>) -> String {
+    let index = self
+      .subject_list
+      .iter()
+      .position(|x| Arc::ptr_eq(x, &subject.upgrade().unwrap()))
+      .unwrap();
+    format!(
+      "subjects/{}-{}.html",
+      index,
+      slug(&self.subject_list[index].name)
+    )
+  }
+  fn student_page(&self, student: &Weak<Student>) -> String {
+    let index = self
+      .student_list
+      .iter()
+      .position(|x| Arc::ptr_eq(x, &student.upgrade().unwrap()))
+      .unwrap();
+    format!(
+      "students/{}-{}.html",
+      index,
+      slug(&self.student_list[index].id)
+    )
+  }
+
+  fn subject_link(&self, subject: &Weak<Subject>, root: &str) -> String {
+    format!(
+      "<a href=\"{}{}\">{}</a>",
+      root,
+      self.subject_page(subject),
+      escape_html(&subject.upgrade().unwrap().name)
+    )
+  }
+  fn student_link(&self, student: &Weak<Student>, root: &str) -> String {
+    let student_arc = student.upgrade().unwrap();
+    format!(
+      "<a href=\"{}{}\">{} {} ({})</a>",
+      root,
+      self.student_page(student),
+      escape_html(&student_arc.first_name),
+      escape_html(&student_arc.last_name),
+      escape_html(&student_arc.id)
+    )
+  }
+
+  // Pages of the site, as paths relative to its root with their contents
+  pub fn html_site(&self) -> Vec<(String, String)> {
+    let report = self.report(&self.student_list);
+    let mut site = vec![("index.html".to_string(), self.overview_html(&report))];
+    for subject in &self.subject_list {
+      let subject = Arc::downgrade(subject);
+      site.push((self.subject_page(&subject), self.subject_html(&subject)));
+    }
+    for (student, student_report) in self.student_list.iter().zip(&report.student_report_list) {
+      let student = Arc::downgrade(student);
+      site.push((
+        self.student_page(&student),
+        self.student_html(&student, student_report),
+      ));
+    }
+    site
+  }
+
+  // Writes the site into the directory, creating it if needed
+  pub fn write_html_site(&self, directory: impl AsRef<Path>) -> Result<()> {
+    let directory = directory.as_ref();
+    for (path, content) in self.html_site() {
+      let path = directory.join(path);
+      if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+          .map_err(|e| Error::Generic(format!("Cannot create {}: {}", parent.display(), e)))?;
+      }
+      std::fs::write(&path, content)
+        .map_err(|e| Error::Generic(format!("Cannot write {}: {}", path.display(), e)))?;
+    }
+    Ok(())
+  }
+
+  fn overview_html(&self, report: &SortReport) -> String {
+    let mut body = String::new();
+    writeln!(body, "<pre>{}</pre>", escape_html(&report.to_string())).unwrap();
+
+    // Classes of each department in each slot, with their sizes
+    body.push_str("<h2>Classes</h2>\n<table>\n<tr><th>Slot</th>");
+    for department in &self.department_list {
+      write!(body, "<th>{}</th>", escape_html(&department.name)).unwrap();
+    }
+    body.push_str("</tr>\n");
+    for (slot_index, slot) in self.timetable.slot_list.iter().enumerate() {
+      write!(body, "<tr><th>{}</th>", slot_index).unwrap();
+      let slot = slot.lock().unwrap();
+      for department in &self.department_list {
+        let department = Arc::downgrade(department);
+        let cell_list: Vec<String> = slot
+          .class_list
+          .iter()
+          .filter(|x| !x.removed && x.department.ptr_eq(&department))
+          .map(|x| {
+            format!(
+              "{} ({})",
+              self.subject_link(&x.subject, ""),
+              x.student_list.len()
+            )
+          })
+          .collect();
+        write!(body, "<td>{}</td>", cell_list.join("<br>")).unwrap();
+      }
+      body.push_str("</tr>\n");
+    }
+    body.push_str("</table>\n");
+
+    body.push_str("<h2>Subjects</h2>\n<ul>\n");
+    for subject in &self.subject_list {
+      let subject = Arc::downgrade(subject);
+      writeln!(body, "<li>{}</li>", self.subject_link(&subject, "")).unwrap();
+    }
+    body.push_str("</ul>\n<h2>Students</h2>\n<ul>\n");
+    for (student, student_report) in self.student_list.iter().zip(&report.student_report_list) {
+      let student = Arc::downgrade(student);
+      let class = if student_report.is_fully_placed() {
+        ""
+      } else {
+        " class=\"unplaced\""
+      };
+      writeln!(
+        body,
+        "<li{}>{}</li>",
+        class,
+        self.student_link(&student, "")
+      )
+      .unwrap();
+    }
+    body.push_str("</ul>\n");
+    page("Timetable", "", &body)
+  }
+
+  fn subject_html(&self, subject: &Weak<Subject>) -> String {
+    let mut body = String::new();
+    body.push_str(
+      "<table>\n<tr><th>Slot</th><th>Department</th><th>Size</th><th>Teacher</th>\
+       <th>Room</th><th>Students</th></tr>\n",
+    );
+    for (slot_index, slot) in self.timetable.slot_list.iter().enumerate() {
+      for class in &slot.lock().unwrap().class_list {
+        if !class.subject.ptr_eq(subject) {
+          continue;
+        }
+        let row_class = if class.removed {
+          " class=\"removed\""
+        } else {
+          ""
+        };
+        let student_list: Vec<String> = class
+          .student_list
+          .iter()
+          .map(|x| self.student_link(x, "../"))
+          .collect();
+        writeln!(
+          body,
+          "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+          row_class,
+          slot_index,
+          escape_html(&class.department.upgrade().unwrap().name),
+          class.student_list.len(),
+          teacher_name(class),
+          room_name(class),
+          student_list.join("<br>")
+        )
+        .unwrap();
+      }
+    }
+    body.push_str("</table>\n<p>Struck out classes were removed for being too small.</p>\n");
+    page(&subject.upgrade().unwrap().name, "../", &body)
+  }
+
+  fn student_html(&self, student: &Weak<Student>, student_report: &StudentReport) -> String {
+    let student_arc = student.upgrade().unwrap();
+    let mut body = String::new();
+    writeln!(body, "<p>Id: {}</p>", escape_html(&student_arc.id)).unwrap();
+    body.push_str(
+      "<table>\n<tr><th>Slot</th><th>Subject</th><th>Department</th><th>Teacher</th>\
+       <th>Room</th></tr>\n",
+    );
+    for (slot_index, slot) in self.timetable.slot_list.iter().enumerate() {
+      let slot = slot.lock().unwrap();
+      match slot
+        .class_list
+        .iter()
+        .find(|x| !x.removed && x.contains(Weak::clone(student)))
+      {
+        Some(class) => writeln!(
+          body,
+          "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+          slot_index,
+          self.subject_link(&class.subject, "../"),
+          escape_html(&class.department.upgrade().unwrap().name),
+          teacher_name(class),
+          room_name(class)
+        ),
+        None => writeln!(
+          body,
+          "<tr><td>{}</td><td colspan=\"4\">Free</td></tr>",
+          slot_index
+        ),
+      }
+      .unwrap();
+    }
+    body.push_str("</table>\n");
+
+    let unplaced_list: Vec<&Weak<Subject>> = student_report
+      .dropped
+      .iter()
+      .chain(&student_report.unplaced)
+      .collect();
+    if !unplaced_list.is_empty() {
+      body.push_str("<h2>Unplaced subjects</h2>\n<ul>\n");
+      for subject in unplaced_list {
+        writeln!(
+          body,
+          "<li class=\"unplaced\">{}</li>",
+          self.subject_link(subject, "../")
+        )
+        .unwrap();
+      }
+      body.push_str("</ul>\n");
+    }
+    if !student_report.alternative_list.is_empty() {
+      body.push_str("<h2>Combinations that would fit</h2>\n<ul>\n");
+      for combination in &student_report.alternative_list {
+        let name_list: Vec<String> = combination
+          .iter()
+          .map(|x| self.subject_link(x, "../"))
+          .collect();
+        writeln!(body, "<li>{}</li>", name_list.join(", ")).unwrap();
+      }
+      body.push_str("</ul>\n");
+    }
+    page(
+      &format!("{} {}", student_arc.first_name, student_arc.last_name),
+      "../",
+      &body,
+    )
+  }
+}
+
+fn teacher_name(class: &Class) -> String {
+  class
+    .teacher
+    .as_ref()
+    .and_then(|x| x.upgrade())
+    .map_or(String::new(), |x| escape_html(&x.name))
+}
+
+fn room_name(class: &Class) -> String {
+  class
+    .room
+    .as_ref()
+    .and_then(|x| x.upgrade())
+    .map_or(String::new(), |x| escape_html(&x.name))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_html_site() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 2, 30, 1).unwrap();
+    high_school.new_department("Science", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject("Physics & Astronomy", vec!["Science"])
+      .unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Physics & Astronomy"])
+      .unwrap();
+    high_school
+      .new_student(
+        "Person",
+        "Two",
+        "2",
+        vec!["Physics & Astronomy", "Calculus"],
+      )
+      .unwrap();
+    high_school.sort().unwrap();
+
+    let site = high_school.html_site();
+    let path_list: Vec<&str> = site.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(
+      path_list,
+      vec![
+        "index.html",
+        "subjects/0-calculus.html",
+        "subjects/1-physics---astronomy.html",
+        "students/0-1.html",
+        "students/1-2.html",
+      ]
+    );
+    let index = &site[0].1;
+    assert!(index
+      .contains("<a href=\"subjects/1-physics---astronomy.html\">Physics &amp; Astronomy</a> (2)"));
+    // Calculus is too small to run, so Person Two's page highlights it
+    let student_page = &site[4].1;
+    assert!(student_page.contains(
+      "<li class=\"unplaced\"><a href=\"../subjects/0-calculus.html\">Calculus</a></li>"
+    ));
+    assert!(!site[3].1.contains("class=\"unplaced\""));
+    assert!(site[1].1.contains("<tr class=\"removed\">"));
+  }
+}
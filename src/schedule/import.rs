@@ -0,0 +1,244 @@
+//! Importing student subject selections from CSV exports of a student management system
+
+use super::Schedule;
+use crate::prelude::*;
+use std::{fmt, io::Read};
+
+// Which columns, counted from 0, hold each part of a student's selection
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvColumns {
+  pub id: usize,
+  pub first_name: usize,
+  pub last_name: usize,
+  // Columns of subject choices in order of preference. When empty, every column after the id
+  // and name columns holds a choice.
+  pub subject_list: Vec<usize>,
+  // Whether the first row names the columns rather than holding a student
+  pub has_headers: bool,
+}
+
+impl Default for CsvColumns {
+  fn default() -> Self {
+    Self {
+      id: 0,
+      first_name: 1,
+      last_name: 2,
+      subject_list: Vec::new(),
+      has_headers: true,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportProblem {
+  MissingId,
+  MissingName,
+  // A subject, as written in the file, that matches no subject of the school
+  UnknownSubject(String),
+  DuplicateId(String),
+  // The row could not be read, or the school refused the student
+  Rejected(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError {
+  // Line of the file the row starts on, counted from 1
+  pub line: u64,
+  pub problem: ImportProblem,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+  pub imported_count: usize,
+  pub error_list: Vec<ImportError>,
+}
+
+impl fmt::Display for ImportError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Line {}: ", self.line)?;
+    match &self.problem {
+      ImportProblem::MissingId => write!(f, "no student id"),
+      ImportProblem::MissingName => write!(f, "no first or last name"),
+      ImportProblem::UnknownSubject(name) => write!(f, "no subject called ({})", name),
+      ImportProblem::DuplicateId(id) => write!(f, "student id ({}) is already in use", id),
+      ImportProblem::Rejected(message) => write!(f, "{}", message),
+    }
+  }
+}
+
+impl fmt::Display for ImportReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "Imported {} students, {} rows with errors",
+      self.imported_count,
+      self.error_list.len()
+    )?;
+    for error in &self.error_list {
+      writeln!(f, "  {}", error)?;
+    }
+    Ok(())
+  }
+}
+
+impl Schedule {
+  // Adds a student for each row, matching subjects by name with surrounding space and case
+  // ignored. Rows that cannot be added are listed in the report and the rest still are.
+  pub fn import_students_csv(
+    &mut self,
+    reader: impl Read,
+    columns: &CsvColumns,
+  ) -> Result<ImportReport> {
+    let mut csv_reader = csv::ReaderBuilder::new()
+      .has_headers(columns.has_headers)
+      .flexible(true)
+      .from_reader(reader);
+    let mut report = ImportReport::default();
+    for record in csv_reader.records() {
+      let record = match record {
+        Ok(k) => k,
+        Err(e) => {
+          let Some(position) = e.position() else {
+            return Err(Error::Generic(format!("Cannot read CSV: {}", e)));
+          };
+          report.error_list.push(ImportError {
+            line: position.line(),
+            problem: ImportProblem::Rejected(e.to_string()),
+          });
+          continue;
+        }
+      };
+      let line = record.position().map_or(0, |x| x.line());
+      match self.import_row(&record, columns) {
+        Ok(()) => report.imported_count += 1,
+        Err(problem) => report.error_list.push(ImportError { line, problem }),
+      }
+    }
+    Ok(report)
+  }
+
+  fn import_row(
+    &mut self,
+    record: &csv::StringRecord,
+    columns: &CsvColumns,
+  ) -> std::result::Result<(), ImportProblem> {
+    let cell = |column: usize| record.get(column).unwrap_or("").trim();
+    let id = cell(columns.id);
+    if id.is_empty() {
+      return Err(ImportProblem::MissingId);
+    }
+    let (first_name, last_name) = (cell(columns.first_name), cell(columns.last_name));
+    if first_name.is_empty() || last_name.is_empty() {
+      return Err(ImportProblem::MissingName);
+    }
+    if self.get_student(id).is_some() {
+      return Err(ImportProblem::DuplicateId(id.to_string()));
+    }
+
+    let subject_column_list: Vec<usize> = if columns.subject_list.is_empty() {
+      let start = columns.id.max(columns.first_name).max(columns.last_name) + 1;
+      (start..record.len()).collect()
+    } else {
+      columns.subject_list.clone()
+    };
+    let mut subject_list = Vec::new();
+    for column in subject_column_list {
+      let name = cell(column);
+      if name.is_empty() {
+        continue;
+      }
+      let Some(subject) = self
+        .subject_list
+        .iter()
+        .find(|x| x.name.trim().to_lowercase() == name.to_lowercase())
+      else {
+        return Err(ImportProblem::UnknownSubject(name.to_string()));
+      };
+      subject_list.push(subject.name.clone());
+    }
+    self
+      .new_student(first_name, last_name, id, subject_list)
+      .map_err(|e| ImportProblem::Rejected(e.to_string()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn school() -> Schedule {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 1, 30, 2).unwrap();
+    high_school.new_department("Science", 1, 30, 2).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school
+      .new_subject("Chemistry", vec!["Science"])
+      .unwrap();
+    high_school
+  }
+
+  #[test]
+  fn test_import_students() {
+    let mut high_school = school();
+    let text = "\
+Id,First,Last,Subject 1,Subject 2,Subject 3
+1,Person,One, calculus ,PHYSICS,
+2,Person,Two,Chemistry,Latin,
+3,,Three,Physics
+1,Person,Again,Physics
+4,Person,Four,Chemistry,Physics,Calculus
+";
+    let report = high_school
+      .import_students_csv(text.as_bytes(), &CsvColumns::default())
+      .unwrap();
+    assert_eq!(report.imported_count, 2);
+    assert_eq!(
+      report.error_list,
+      vec![
+        ImportError {
+          line: 3,
+          problem: ImportProblem::UnknownSubject("Latin".to_string()),
+        },
+        ImportError {
+          line: 4,
+          problem: ImportProblem::MissingName,
+        },
+        ImportError {
+          line: 5,
+          problem: ImportProblem::DuplicateId("1".to_string()),
+        },
+      ]
+    );
+    let student = high_school.get_student("1").unwrap();
+    assert_eq!(student.subject_list.len(), 2);
+    assert_eq!(
+      student.subject_list[0].upgrade().unwrap().name,
+      "Calculus".to_string()
+    );
+    assert_eq!(high_school.get_student("4").unwrap().subject_list.len(), 3);
+  }
+
+  #[test]
+  fn test_import_column_mapping() {
+    let mut high_school = school();
+    let text = "Physics,One,Person,1,ignored,Calculus\n";
+    let columns = CsvColumns {
+      id: 3,
+      first_name: 2,
+      last_name: 1,
+      subject_list: vec![5, 0],
+      has_headers: false,
+    };
+    let report = high_school
+      .import_students_csv(text.as_bytes(), &columns)
+      .unwrap();
+    assert!(report.error_list.is_empty());
+    let student = high_school.get_student("1").unwrap();
+    assert_eq!(student.first_name, "Person");
+    assert_eq!(
+      student.subject_list[1].upgrade().unwrap().name,
+      "Physics".to_string()
+    );
+  }
+}
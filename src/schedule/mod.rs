@@ -1,33 +1,127 @@
+pub mod cohort;
 pub mod department;
+pub mod export;
+pub mod file;
+pub mod html;
+pub mod import;
 pub mod random;
+pub mod report;
+pub mod room;
+pub mod rules;
 pub mod student;
 pub mod subject;
+pub mod teacher;
 
 extern crate rand;
 
+pub use cohort::{Cohort, CohortBuilder};
 pub use department::Department;
+pub use file::{
+  BlockEntryFile, BlockFile, ClassFile, CohortFile, RoomFile, SchoolFile, SlotFile, StudentFile,
+  SubjectFile, TeacherFile, FILE_VERSION,
+};
+pub use import::{CsvColumns, ImportError, ImportProblem, ImportReport};
+pub use report::{SortReport, StudentReport};
+pub use room::{Room, RoomBuilder};
+pub use rules::{RuleBreach, RuleViolation, SubjectRules};
 pub use student::{Student, StudentBuilder};
 pub use subject::{Subject, SubjectBuilder};
+pub use teacher::{Teacher, TeacherBuilder};
 
-use crate::{prelude::*, timetable::Timetable, Class};
-use std::sync::Arc;
+use crate::{
+  alternative_combinations, prelude::*, timetable::Timetable, AnnealConfig, BlockSolver,
+  BlockingPlan, Class, GreedySolver, LocalSearchSolver, ObjectiveWeights, PeriodGrid, PeriodMap,
+  Solver, Term, WeekGrid,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::sync::{Arc, Weak};
+
+// Slots in a schedule made with Schedule::new
+pub const DEFAULT_SLOT_COUNT: usize = 5;
 
 #[derive(Default)]
 pub struct Schedule {
   pub student_list: Vec<Arc<Student>>,
   pub subject_list: Vec<Arc<Subject>>,
   pub department_list: Vec<Arc<Department>>,
+  pub teacher_list: Vec<Arc<Teacher>>,
+  // When empty, each department's class_count stands in for its rooms
+  pub room_list: Vec<Arc<Room>>,
+  // Year groups, each taught in its own slots. Schools without any treat every student alike.
+  pub cohort_list: Vec<Arc<Cohort>>,
   pub timetable: Timetable,
+  // When set, sorting finishes with a simulated annealing pass over the greedy timetable
+  pub anneal_config: Option<AnnealConfig>,
+  // Days and periods each slot meets in, once a period grid has been set
+  pub period_map: Option<PeriodMap>,
+  // Classes fixed in each slot before sorting. When set, students are only placed into these.
+  pub blocking_plan: Option<BlockingPlan>,
+  // How timetables are scored in reports and by the solvers that compare timetables
+  pub objective_weights: ObjectiveWeights,
+  // Seeds the random choices solvers make, so a sort can be replayed exactly
+  pub seed: Option<u64>,
 }
 
 impl Schedule {
   pub fn new() -> Self {
+    Self::with_slot_count(DEFAULT_SLOT_COUNT)
+  }
+
+  pub fn with_slot_count(slot_count: usize) -> Self {
     Self {
-      timetable: Timetable::new(5),
+      timetable: Timetable::new(slot_count),
       ..Default::default()
     }
   }
 
+  // Number of slots (option lines) every student's subjects are spread across
+  pub fn slot_count(&self) -> usize {
+    self.timetable.slot_list.len()
+  }
+
+  // Replaces the timetable with an empty one of the new size, and lays the new slots out over
+  // the period grid if one was set
+  pub fn set_slot_count(&mut self, slot_count: usize) -> Result<()> {
+    for student in &self.student_list {
+      check_slot_demand(student, slot_count)?;
+    }
+    for cohort in &self.cohort_list {
+      if cohort.slot_list.iter().any(|&slot| slot >= slot_count) {
+        return Err(Error::Generic(format!(
+          "Cohort ({}) is taught in slots beyond the {} slots",
+          cohort.name, slot_count
+        )));
+      }
+    }
+    if let Some(plan) = &self.blocking_plan {
+      if plan.block_list.len() != slot_count {
+        return Err(Error::Generic(format!(
+          "Blocking plan has {} blocks, which cannot be spread over {} slots",
+          plan.block_list.len(),
+          slot_count
+        )));
+      }
+    }
+    let period_map = match &self.period_map {
+      Some(period_map) => Some(period_map.grid.map_slots(
+        slot_count,
+        period_map.slot_period_list.first().map_or(0, |x| x.len()),
+      )?),
+      None => None,
+    };
+    self.timetable = Timetable::new(slot_count);
+    self.period_map = period_map;
+    Ok(())
+  }
+
+  // Random number generator for solvers, seeded from the schedule's seed when it has one
+  pub fn rng(&self) -> StdRng {
+    match self.seed {
+      Some(seed) => StdRng::seed_from_u64(seed),
+      None => StdRng::from_entropy(),
+    }
+  }
+
   fn department_name_in_use(&self, name: impl Into<String>) -> bool {
     let name = name.into();
     for element in &self.department_list {
@@ -65,7 +159,7 @@ impl Schedule {
     })
   }
 
-  fn get_department(&self, name: impl Into<String>) -> Option<Arc<Department>> {
+  pub fn get_department(&self, name: impl Into<String>) -> Option<Arc<Department>> {
     let name = name.into();
     for element in &self.department_list {
       if element.name == name {
@@ -97,7 +191,7 @@ impl Schedule {
     Ok(())
   }
 
-  fn get_subject(&self, name: impl Into<String>) -> Option<Arc<Subject>> {
+  pub fn get_subject(&self, name: impl Into<String>) -> Option<Arc<Subject>> {
     let name = name.into();
     for element in &self.subject_list {
       if element.name == name {
@@ -112,7 +206,93 @@ impl Schedule {
     name: impl Into<String>,
     departments: Vec<impl Into<String>>,
   ) -> Result<()> {
-    let mut subject_builder = SubjectBuilder::new().name(name);
+    self.add_subject(self.build_subject(name, departments, SubjectBuilder::new())?)
+  }
+
+  // Adds a subject whose classes can only be held in rooms with all of the features
+  pub fn new_subject_with_features(
+    &mut self,
+    name: impl Into<String>,
+    departments: Vec<impl Into<String>>,
+    features: Vec<impl Into<String>>,
+  ) -> Result<()> {
+    let mut subject_builder = SubjectBuilder::new();
+    for feature in features {
+      subject_builder = subject_builder.feature(feature);
+    }
+    self.add_subject(self.build_subject(name, departments, subject_builder)?)
+  }
+
+  // Adds a subject that students take in slot_demand slots, with the same class in each
+  pub fn new_subject_with_slot_demand(
+    &mut self,
+    name: impl Into<String>,
+    departments: Vec<impl Into<String>>,
+    slot_demand: usize,
+  ) -> Result<()> {
+    let subject_builder = SubjectBuilder::new().slot_demand(slot_demand);
+    self.add_subject(self.build_subject(name, departments, subject_builder)?)
+  }
+
+  // Adds a subject that limits which other subjects its students take, and what they must
+  // have completed before
+  pub fn new_subject_with_rules(
+    &mut self,
+    name: impl Into<String>,
+    departments: Vec<impl Into<String>>,
+    rules: SubjectRules,
+  ) -> Result<()> {
+    let subject_builder = self.rules_builder(SubjectBuilder::new(), rules)?;
+    self.add_subject(self.build_subject(name, departments, subject_builder)?)
+  }
+
+  fn rules_builder(
+    &self,
+    subject_builder: SubjectBuilder,
+    rules: SubjectRules,
+  ) -> Result<SubjectBuilder> {
+    let mut subject_builder = subject_builder;
+    for prerequisite in rules.prerequisite_list {
+      subject_builder = subject_builder.prerequisite(prerequisite);
+    }
+    for name in rules.corequisite_list {
+      let Some(corequisite) = self.get_subject(&name) else {
+        return Err(Error::Generic(format!(
+          "No subject with name ({}) found for corequisite",
+          name
+        )));
+      };
+      subject_builder = subject_builder.corequisite(Arc::downgrade(&corequisite));
+    }
+    for name in rules.exclusion_list {
+      let Some(exclusion) = self.get_subject(&name) else {
+        return Err(Error::Generic(format!(
+          "No subject with name ({}) found for exclusion",
+          name
+        )));
+      };
+      subject_builder = subject_builder.exclusion(Arc::downgrade(&exclusion));
+    }
+    Ok(subject_builder)
+  }
+
+  // Adds a subject that every student who takes it must be placed in
+  pub fn new_compulsory_subject(
+    &mut self,
+    name: impl Into<String>,
+    departments: Vec<impl Into<String>>,
+  ) -> Result<()> {
+    let subject_builder = SubjectBuilder::new().compulsory(true);
+    self.add_subject(self.build_subject(name, departments, subject_builder)?)
+  }
+
+  fn build_subject(
+    &self,
+    name: impl Into<String>,
+    departments: Vec<impl Into<String>>,
+    subject_builder: SubjectBuilder,
+  ) -> Result<Subject> {
+    let mut subject_builder = subject_builder.name(name);
 
     for element in departments {
       let element = element.into();
@@ -128,12 +308,7 @@ impl Schedule {
       subject_builder = subject_builder.department(Arc::downgrade(&department));
     }
 
-    let subject = match subject_builder.build() {
-      Ok(k) => k,
-      Err(k) => return Err(k),
-    };
-
-    self.add_subject(subject)
+    subject_builder.build()
   }
 
   fn student_id_in_use(&self, student_id: impl Into<String>) -> bool {
@@ -147,6 +322,18 @@ impl Schedule {
   }
 
   fn add_student(&mut self, student: Student) -> Result<()> {
+    check_slot_demand(&student, self.slot_count())?;
+    if let Some(cohort) = student.cohort.as_ref().map(|x| x.upgrade().unwrap()) {
+      if let Some(subject) = student.subject_list.iter().find(|x| !cohort.offers(x)) {
+        return Err(Error::Generic(format!(
+          "Subject ({}) is not offered to cohort ({}) of student (id: {})",
+          subject.upgrade().unwrap().name,
+          cohort.name,
+          student.id
+        )));
+      }
+    }
+    violation_check(&rules::rule_violations(&student))?;
     if self.student_id_in_use(&student.id) {
       return Err(Error::Generic(format!(
         "Student with id ({}) already exists",
@@ -157,7 +344,17 @@ impl Schedule {
     Ok(())
   }
 
-  fn get_student(&self, student_id: impl Into<String>) -> Option<Arc<Student>> {
+  // Every subject rule broken by a student's choices. Students added through the schedule are
+  // already checked, but ones pushed straight into student_list are not until they are sorted.
+  pub fn validate(&self) -> Vec<RuleViolation> {
+    self
+      .student_list
+      .iter()
+      .flat_map(|student| rules::rule_violations(student))
+      .collect()
+  }
+
+  pub fn get_student(&self, student_id: impl Into<String>) -> Option<Arc<Student>> {
     let student_id = student_id.into();
     for element in &self.student_list {
       if element.id == student_id {
@@ -174,6 +371,114 @@ impl Schedule {
     id: impl Into<String>,
     subjects: Vec<impl Into<String>>,
   ) -> Result<()> {
+    self.new_student_with_reserves(first_name, last_name, id, subjects, Vec::<String>::new())
+  }
+
+  // Subjects are the student's choices from first to last, and reserves are taken in order in
+  // place of choices that cannot be placed
+  pub fn new_student_with_reserves(
+    &mut self,
+    first_name: impl Into<String>,
+    last_name: impl Into<String>,
+    id: impl Into<String>,
+    subjects: Vec<impl Into<String>>,
+    reserves: Vec<impl Into<String>>,
+  ) -> Result<()> {
+    let student = self
+      .student_builder(
+        first_name,
+        last_name,
+        id,
+        subjects,
+        reserves,
+        Vec::<String>::new(),
+      )?
+      .build()?;
+    self.add_student(student)
+  }
+
+  // Adds a student who must be placed in the compulsory subjects out of their choices
+  pub fn new_student_with_compulsory(
+    &mut self,
+    first_name: impl Into<String>,
+    last_name: impl Into<String>,
+    id: impl Into<String>,
+    subjects: Vec<impl Into<String>>,
+    compulsory: Vec<impl Into<String>>,
+  ) -> Result<()> {
+    let student = self
+      .student_builder(
+        first_name,
+        last_name,
+        id,
+        subjects,
+        Vec::<String>::new(),
+        compulsory,
+      )?
+      .build()?;
+    self.add_student(student)
+  }
+
+  // Adds a student along with the subjects they completed in earlier years, which count
+  // towards prerequisites
+  pub fn new_student_with_completed(
+    &mut self,
+    first_name: impl Into<String>,
+    last_name: impl Into<String>,
+    id: impl Into<String>,
+    subjects: Vec<impl Into<String>>,
+    completed: Vec<impl Into<String>>,
+  ) -> Result<()> {
+    let mut student_builder = self.student_builder(
+      first_name,
+      last_name,
+      id,
+      subjects,
+      Vec::<String>::new(),
+      Vec::<String>::new(),
+    )?;
+    for subject_name in completed {
+      student_builder = student_builder.completed(subject_name);
+    }
+    self.add_student(student_builder.build()?)
+  }
+
+  // Adds a student to a cohort, choosing from the subjects it offers
+  pub fn new_student_in_cohort(
+    &mut self,
+    first_name: impl Into<String>,
+    last_name: impl Into<String>,
+    id: impl Into<String>,
+    cohort_name: impl Into<String>,
+    subjects: Vec<impl Into<String>>,
+  ) -> Result<()> {
+    let cohort_name = cohort_name.into();
+    let Some(cohort) = self.get_cohort(&cohort_name) else {
+      return Err(Error::Generic(format!(
+        "No cohort with name ({}) found",
+        cohort_name
+      )));
+    };
+    let student_builder = self.student_builder(
+      first_name,
+      last_name,
+      id,
+      subjects,
+      Vec::<String>::new(),
+      Vec::<String>::new(),
+    )?;
+    self.add_student(student_builder.cohort(Arc::downgrade(&cohort)).build()?)
+  }
+
+  fn student_builder(
+    &self,
+    first_name: impl Into<String>,
+    last_name: impl Into<String>,
+    id: impl Into<String>,
+    subjects: Vec<impl Into<String>>,
+    reserves: Vec<impl Into<String>>,
+    compulsory: Vec<impl Into<String>>,
+  ) -> Result<StudentBuilder> {
     let first_name = first_name.into();
     let last_name = last_name.into();
     let id = id.into();
@@ -190,33 +495,692 @@ impl Schedule {
 
       student_builder = student_builder.subject(Arc::downgrade(&element));
     }
-    let student = match student_builder.build() {
-      Ok(k) => k,
-      Err(k) => return Err(k),
+    for subject_name in reserves {
+      let subject_name = subject_name.into();
+      let element = match self.get_subject(&subject_name) {
+        Some(k) => k,
+        None => {
+          return Err(Error::Generic(format!(
+            "Tried adding reserve ({}) to student (name: {} {}, id: {}) when no subject with that name exists",
+            subject_name, first_name, last_name, id
+          )))
+        }
+      };
+      student_builder = student_builder.reserve(Arc::downgrade(&element));
+    }
+    for subject_name in compulsory {
+      let subject_name = subject_name.into();
+      let element = match self.get_subject(&subject_name) {
+        Some(k) => k,
+        None => {
+          return Err(Error::Generic(format!(
+            "Tried making subject ({}) compulsory for student (name: {} {}, id: {}) when no subject with that name exists",
+            subject_name, first_name, last_name, id
+          )))
+        }
+      };
+      student_builder = student_builder.compulsory(Arc::downgrade(&element));
+    }
+    Ok(student_builder)
+  }
+
+  fn teacher_id_in_use(&self, teacher_id: impl Into<String>) -> bool {
+    let teacher_id = teacher_id.into();
+    for element in &self.teacher_list {
+      if element.id == teacher_id {
+        return true;
+      }
+    }
+    false
+  }
+
+  fn add_teacher(&mut self, teacher: Teacher) -> Result<()> {
+    if self.teacher_id_in_use(&teacher.id) {
+      return Err(Error::Generic(format!(
+        "Teacher with id ({}) already exists",
+        teacher.id.clone()
+      )));
     };
-    self.add_student(student)
+    self.teacher_list.push(Arc::new(teacher));
+    Ok(())
   }
 
-  pub fn sort(&mut self) -> Result<()> {
-    self.timetable.clear();
+  pub fn get_teacher(&self, teacher_id: impl Into<String>) -> Option<Arc<Teacher>> {
+    let teacher_id = teacher_id.into();
+    for element in &self.teacher_list {
+      if element.id == teacher_id {
+        return Some(Arc::clone(element));
+      }
+    }
+    None
+  }
 
-    for student in &self.student_list {
-      self
-        .timetable
-        .add_student_to_timetable(Arc::downgrade(&student))
-        .unwrap();
+  pub fn new_teacher(
+    &mut self,
+    id: impl Into<String>,
+    name: impl Into<String>,
+    subjects: Vec<impl Into<String>>,
+    departments: Vec<impl Into<String>>,
+    max_class_count: usize,
+  ) -> Result<()> {
+    let mut teacher_builder = TeacherBuilder::new()
+      .id(id)
+      .name(name)
+      .max_class_count(max_class_count);
+    for subject_name in subjects {
+      let subject_name = subject_name.into();
+      let subject = match self.get_subject(&subject_name) {
+        Some(k) => k,
+        None => {
+          return Err(Error::Generic(format!(
+            "No subject with name ({}) found",
+            subject_name
+          )))
+        }
+      };
+      teacher_builder = teacher_builder.subject(Arc::downgrade(&subject));
     }
+    for department_name in departments {
+      let department_name = department_name.into();
+      let department = match self.get_department(&department_name) {
+        Some(k) => k,
+        None => {
+          return Err(Error::Generic(format!(
+            "No department with name ({}) found",
+            department_name
+          )))
+        }
+      };
+      teacher_builder = teacher_builder.department(Arc::downgrade(&department));
+    }
+    let teacher = teacher_builder.build()?;
+    self.add_teacher(teacher)
+  }
 
-    // Remove classes with insufficient class size
-    self.timetable.slot_list.iter_mut().for_each(|slot| {
-      slot
-        .lock()
-        .unwrap()
-        .remove_small_classes_and_get_displaced_students();
-    });
+  fn room_name_in_use(&self, name: impl Into<String>) -> bool {
+    let name = name.into();
+    for element in &self.room_list {
+      if element.name == name {
+        return true;
+      }
+    }
+    false
+  }
 
+  fn add_room(&mut self, room: Room) -> Result<()> {
+    let name = &room.name;
+    if self.room_name_in_use(name) {
+      return Err(Error::Generic(format!(
+        "Name ({}) is already in use by another room",
+        name
+      )));
+    };
+    self.room_list.push(Arc::new(room));
     Ok(())
   }
+
+  pub fn get_room(&self, name: impl Into<String>) -> Option<Arc<Room>> {
+    let name = name.into();
+    for element in &self.room_list {
+      if element.name == name {
+        return Some(Arc::clone(element));
+      }
+    }
+    None
+  }
+
+  pub fn new_room(
+    &mut self,
+    name: impl Into<String>,
+    capacity: usize,
+    departments: Vec<impl Into<String>>,
+    features: Vec<impl Into<String>>,
+  ) -> Result<()> {
+    let mut room_builder = RoomBuilder::new().name(name).capacity(capacity);
+    for department_name in departments {
+      let department_name = department_name.into();
+      let department = match self.get_department(&department_name) {
+        Some(k) => k,
+        None => {
+          return Err(Error::Generic(format!(
+            "No department with name ({}) found",
+            department_name
+          )))
+        }
+      };
+      room_builder = room_builder.department(Arc::downgrade(&department));
+    }
+    for feature in features {
+      room_builder = room_builder.feature(feature);
+    }
+    let room = room_builder.build()?;
+    self.add_room(room)
+  }
+
+  fn add_cohort(&mut self, cohort: Cohort) -> Result<()> {
+    if self.get_cohort(&cohort.name).is_some() {
+      return Err(Error::Generic(format!(
+        "Name ({}) is already in use by another cohort",
+        cohort.name
+      )));
+    };
+    if let Some(slot) = cohort.slot_list.iter().find(|&&x| x >= self.slot_count()) {
+      return Err(Error::Generic(format!(
+        "Cohort ({}) uses slot {} but there are only {} slots",
+        cohort.name,
+        slot,
+        self.slot_count()
+      )));
+    }
+    self.cohort_list.push(Arc::new(cohort));
+    Ok(())
+  }
+
+  pub fn get_cohort(&self, name: impl Into<String>) -> Option<Arc<Cohort>> {
+    let name = name.into();
+    for element in &self.cohort_list {
+      if element.name == name {
+        return Some(Arc::clone(element));
+      }
+    }
+    None
+  }
+
+  // Adds a year group offered the subjects and taught in the slots, counted from 0
+  pub fn new_cohort(
+    &mut self,
+    name: impl Into<String>,
+    subjects: Vec<impl Into<String>>,
+    slots: Vec<usize>,
+  ) -> Result<()> {
+    let mut cohort_builder = CohortBuilder::new().name(name);
+    for subject_name in subjects {
+      let subject_name = subject_name.into();
+      let Some(subject) = self.get_subject(&subject_name) else {
+        return Err(Error::Generic(format!(
+          "No subject with name ({}) found",
+          subject_name
+        )));
+      };
+      cohort_builder = cohort_builder.subject(Arc::downgrade(&subject));
+    }
+    for slot in slots {
+      cohort_builder = cohort_builder.slot(slot);
+    }
+    let cohort = cohort_builder.build()?;
+    self.add_cohort(cohort)
+  }
+
+  // Most classes the department can run in one slot, counted from its rooms once any are
+  // declared. Rooms it shares with other departments are included, so use rooms_fit to check
+  // several departments at once.
+  pub fn department_room_count(&self, department: &Arc<Department>) -> usize {
+    if self.room_list.is_empty() {
+      return department.class_count;
+    }
+    let department = Arc::downgrade(department);
+    self
+      .room_list
+      .iter()
+      .filter(|room| room.belongs_to(&department))
+      .count()
+  }
+
+  // Indices into department_list of the departments using each room
+  pub(crate) fn room_department_list(&self) -> Vec<Vec<usize>> {
+    self
+      .room_list
+      .iter()
+      .map(|room| {
+        (0..self.department_list.len())
+          .filter(|&x| room.belongs_to(&Arc::downgrade(&self.department_list[x])))
+          .collect()
+      })
+      .collect()
+  }
+
+  // Whether one slot can run this many classes of each department, given in department_list
+  // order, with every class in a room of its own once any rooms are declared
+  pub fn rooms_fit(&self, class_count_list: &[usize]) -> bool {
+    if self.room_list.is_empty() {
+      return class_count_list
+        .iter()
+        .zip(&self.department_list)
+        .all(|(&count, department)| count <= department.class_count);
+    }
+    room::rooms_fit(&self.room_department_list(), class_count_list)
+  }
+
+  // Classes the school can run in one slot across every department
+  pub fn classroom_count(&self) -> usize {
+    if self.room_list.is_empty() {
+      return self.department_list.iter().map(|x| x.class_count).sum();
+    }
+    self.room_list.len()
+  }
+
+  // Timetable with the schedule's slots and rooms and no classes, for solvers to fill
+  pub fn empty_timetable(&self) -> Timetable {
+    let timetable = Timetable::with_rooms(self.slot_count(), &self.room_list);
+    timetable.set_cohorts(&self.cohort_list);
+    timetable
+  }
+
+  // Lays the slots out over the grid, each meeting periods_per_slot times a cycle
+  pub fn set_period_grid(&mut self, grid: PeriodGrid, periods_per_slot: usize) -> Result<()> {
+    self.period_map = Some(grid.map_slots(self.slot_count(), periods_per_slot)?);
+    Ok(())
+  }
+
+  pub fn student_week(&self, student_id: impl Into<String>) -> Option<WeekGrid> {
+    let student = self.get_student(student_id)?;
+    Some(
+      self
+        .period_map
+        .as_ref()?
+        .student_week(&self.timetable, Arc::downgrade(&student)),
+    )
+  }
+
+  pub fn teacher_week(&self, teacher_id: impl Into<String>) -> Option<WeekGrid> {
+    let teacher = self.get_teacher(teacher_id)?;
+    Some(
+      self
+        .period_map
+        .as_ref()?
+        .teacher_week(&self.timetable, Arc::downgrade(&teacher)),
+    )
+  }
+
+  // Calendar of the student's classes over the term, for importing into a calendar app
+  pub fn student_calendar(&self, student_id: impl Into<String>, term: &Term) -> Result<String> {
+    let student_id = student_id.into();
+    let Some(student) = self.get_student(&student_id) else {
+      return Err(Error::Generic(format!(
+        "No student with id ({}) found",
+        student_id
+      )));
+    };
+    let student = Arc::downgrade(&student);
+    self.calendar(&student_id, term, |_, _, class| {
+      class.contains(Weak::clone(&student))
+    })
+  }
+
+  pub fn teacher_calendar(&self, teacher_id: impl Into<String>, term: &Term) -> Result<String> {
+    let teacher_id = teacher_id.into();
+    let Some(teacher) = self.get_teacher(&teacher_id) else {
+      return Err(Error::Generic(format!(
+        "No teacher with id ({}) found",
+        teacher_id
+      )));
+    };
+    let teacher = Arc::downgrade(&teacher);
+    self.calendar(&teacher_id, term, |_, _, class| {
+      class.teacher.as_ref().is_some_and(|x| x.ptr_eq(&teacher))
+    })
+  }
+
+  // Calendar of one class, given by its slot and its index in the slot's class list. A class
+  // of a multi-slot subject comes with the rest of its group.
+  pub fn class_calendar(&self, slot: usize, class_index: usize, term: &Term) -> Result<String> {
+    let Some(class) = self
+      .timetable
+      .slot_list
+      .get(slot)
+      .and_then(|x| x.lock().unwrap().class_list.get(class_index).cloned())
+    else {
+      return Err(Error::Generic(format!(
+        "No class {} in slot {} found",
+        class_index, slot
+      )));
+    };
+    let name = format!("{} {}", class.subject.upgrade().unwrap().name, slot);
+    self.calendar(&name, term, |other_slot, other_index, other| {
+      match class.group {
+        Some(group) => other.group == Some(group),
+        None => (other_slot, other_index) == (slot, class_index),
+      }
+    })
+  }
+
+  fn calendar(
+    &self,
+    name: &str,
+    term: &Term,
+    filter: impl Fn(usize, usize, &Class) -> bool,
+  ) -> Result<String> {
+    let Some(period_map) = &self.period_map else {
+      return Err(Error::Generic(
+        "Cannot make a calendar before a period grid is set".to_string(),
+      ));
+    };
+    period_map.calendar(&self.timetable, term, name, filter)
+  }
+
+  pub fn set_blocking_plan(&mut self, plan: BlockingPlan) -> Result<()> {
+    if plan.block_list.len() != self.slot_count() {
+      return Err(Error::Generic(format!(
+        "Blocking plan has {} blocks but there are {} slots",
+        plan.block_list.len(),
+        self.slot_count()
+      )));
+    }
+    self.blocking_plan = Some(plan);
+    Ok(())
+  }
+
+  // Fixes class_count classes of the subject, run by the department, in the slot
+  pub fn add_blocked_classes(
+    &mut self,
+    slot: usize,
+    subject_name: impl Into<String>,
+    department_name: impl Into<String>,
+    class_count: usize,
+  ) -> Result<()> {
+    let subject_name = subject_name.into();
+    let department_name = department_name.into();
+    if slot >= self.slot_count() {
+      return Err(Error::Generic(format!(
+        "Slot ({}) is out of range, there are {} slots",
+        slot,
+        self.slot_count()
+      )));
+    }
+    let subject = match self.get_subject(&subject_name) {
+      Some(k) => k,
+      None => {
+        return Err(Error::Generic(format!(
+          "No subject with name ({}) found",
+          subject_name
+        )))
+      }
+    };
+    let department = match self.get_department(&department_name) {
+      Some(k) => k,
+      None => {
+        return Err(Error::Generic(format!(
+          "No department with name ({}) found",
+          department_name
+        )))
+      }
+    };
+    let department = Arc::downgrade(&department);
+    if !subject
+      .department_list
+      .iter()
+      .any(|x| x.ptr_eq(&department))
+    {
+      return Err(Error::Generic(format!(
+        "Subject ({}) is not run by department ({})",
+        subject_name, department_name
+      )));
+    }
+    let slot_count = self.slot_count();
+    let plan = self
+      .blocking_plan
+      .get_or_insert_with(|| BlockingPlan::new(slot_count));
+    for _ in 0..class_count {
+      plan.add_class(slot, Arc::downgrade(&subject), Weak::clone(&department));
+    }
+    Ok(())
+  }
+
+  // Copy of the school with a different slot count and the listed departments running a
+  // different number of classes per slot, with an empty timetable
+  pub fn reconfigured(
+    &self,
+    slot_count: usize,
+    class_count_list: &[(String, usize)],
+  ) -> Result<Schedule> {
+    let name_list = |department_list: &[Weak<Department>]| -> Vec<String> {
+      department_list
+        .iter()
+        .map(|x| x.upgrade().unwrap().name.clone())
+        .collect()
+    };
+    let subject_name_list = |subject_list: &[Weak<Subject>]| -> Vec<String> {
+      subject_list
+        .iter()
+        .map(|x| x.upgrade().unwrap().name.clone())
+        .collect()
+    };
+    let mut schedule = Schedule {
+      anneal_config: self.anneal_config.clone(),
+      objective_weights: self.objective_weights.clone(),
+      seed: self.seed,
+      ..Schedule::with_slot_count(slot_count)
+    };
+    for department in &self.department_list {
+      let class_count = class_count_list
+        .iter()
+        .find(|(name, _)| name == &department.name)
+        .map_or(department.class_count, |(_, class_count)| *class_count);
+      schedule.new_department(
+        &department.name,
+        department.min_class_size,
+        department.max_class_size,
+        class_count,
+      )?;
+    }
+    for subject in &self.subject_list {
+      let mut subject_builder = schedule.rules_builder(
+        SubjectBuilder::new()
+          .slot_demand(subject.slot_demand)
+          .compulsory(subject.compulsory),
+        SubjectRules {
+          prerequisite_list: subject.prerequisite_list.clone(),
+          corequisite_list: subject_name_list(&subject.corequisite_list),
+          exclusion_list: subject_name_list(&subject.exclusion_list),
+        },
+      )?;
+      for feature in &subject.feature_list {
+        subject_builder = subject_builder.feature(feature);
+      }
+      let subject = schedule.build_subject(
+        &subject.name,
+        name_list(&subject.department_list),
+        subject_builder,
+      )?;
+      schedule.add_subject(subject)?;
+    }
+    for cohort in &self.cohort_list {
+      // Each new slot stands for the old slot at the same point through the cycle, so cohorts
+      // keep their share of the slots as the count rises or falls. Cohorts left with none keep
+      // the slot their first one falls on.
+      let mut slot_list: Vec<usize> = (0..slot_count)
+        .filter(|&slot| cohort.uses_slot(slot * self.slot_count() / slot_count))
+        .collect();
+      if slot_list.is_empty() {
+        slot_list.push(cohort.slot_list[0] * slot_count / self.slot_count());
+      }
+      schedule.new_cohort(
+        &cohort.name,
+        subject_name_list(&cohort.subject_list),
+        slot_list,
+      )?;
+    }
+    for student in &self.student_list {
+      let mut student_builder = schedule.student_builder(
+        &student.first_name,
+        &student.last_name,
+        &student.id,
+        subject_name_list(&student.subject_list),
+        subject_name_list(&student.reserve_list),
+        subject_name_list(&student.compulsory_list),
+      )?;
+      for subject_name in &student.completed_list {
+        student_builder = student_builder.completed(subject_name);
+      }
+      if let Some(cohort) = &student.cohort {
+        let cohort = schedule
+          .get_cohort(&cohort.upgrade().unwrap().name)
+          .unwrap();
+        student_builder = student_builder.cohort(Arc::downgrade(&cohort));
+      }
+      schedule.add_student(student_builder.build()?)?;
+    }
+    for teacher in &self.teacher_list {
+      schedule.new_teacher(
+        &teacher.id,
+        &teacher.name,
+        subject_name_list(&teacher.subject_list),
+        name_list(&teacher.department_list),
+        teacher.max_class_count,
+      )?;
+    }
+    for room in &self.room_list {
+      schedule.new_room(
+        &room.name,
+        room.capacity,
+        name_list(&room.department_list),
+        room.feature_list.clone(),
+      )?;
+    }
+    Ok(schedule)
+  }
+
+  // Sorts into the blocking plan when one is set, otherwise lets the classes form on demand
+  pub fn sort(&mut self) -> Result<SortReport> {
+    if self.blocking_plan.is_some() {
+      return self.sort_with(&mut BlockSolver::new());
+    }
+    if let Some(config) = &self.anneal_config {
+      return self.sort_with(&mut LocalSearchSolver::new(config.clone()));
+    }
+    self.sort_with(&mut GreedySolver::new())
+  }
+
+  pub fn sort_with(&mut self, solver: &mut dyn Solver) -> Result<SortReport> {
+    for student in &self.student_list {
+      check_slot_demand(student, self.slot_count())?;
+    }
+    violation_check(&self.validate())?;
+    let mut timetable = solver.solve(self)?;
+    // Compulsory subjects are a hard constraint, so a timetable missing any is not accepted.
+    // A blocking plan fixes the classes, so none can be opened for them.
+    let unmet_list = timetable.place_compulsory(&self.student_list, self.blocking_plan.is_none());
+    if !unmet_list.is_empty() {
+      let mut student_list: Vec<(String, Vec<String>)> = Vec::new();
+      for (student, subject) in unmet_list {
+        let student_id = student.upgrade().unwrap().id.clone();
+        let subject_name = subject.upgrade().unwrap().name.clone();
+        match student_list.iter_mut().find(|(id, _)| *id == student_id) {
+          Some((_, subject_list)) => subject_list.push(subject_name),
+          None => student_list.push((student_id, vec![subject_name])),
+        }
+      }
+      return Err(Error::CompulsoryUnmet(student_list));
+    }
+    self.timetable = timetable;
+    self
+      .timetable
+      .place_reserves(&self.student_list, self.blocking_plan.is_none());
+    if !self.room_list.is_empty() {
+      self.timetable.assign_rooms();
+    }
+    // Schools that have not entered their staff still get a timetable, just without teachers
+    if !self.teacher_list.is_empty() {
+      self.timetable.assign_teachers(&self.teacher_list);
+    }
+    Ok(SortReport {
+      anneal_report: solver.anneal_report(),
+      ..self.report(&self.student_list)
+    })
+  }
+
+  // Report on how the students were placed in the current timetable
+  fn report(&self, student_list: &[Arc<Student>]) -> SortReport {
+    let mut report = SortReport {
+      score: self.timetable.score(student_list, &self.objective_weights),
+      untaught_class_list: if self.teacher_list.is_empty() {
+        Vec::new()
+      } else {
+        self.timetable.untaught_class_list()
+      },
+      unroomed_class_list: if self.room_list.is_empty() {
+        Vec::new()
+      } else {
+        self.timetable.unroomed_class_list()
+      },
+      ..SortReport::new(student_list, &self.timetable)
+    };
+    // Students the plan could not hold get the closest combinations it could
+    if self.blocking_plan.is_some() {
+      for (student, student_report) in student_list.iter().zip(&mut report.student_report_list) {
+        if !student_report.is_fully_placed() {
+          student_report.alternative_list =
+            alternative_combinations(&self.timetable, student, &self.subject_list, 3);
+        }
+      }
+    }
+    report
+  }
+
+  // Report on the current timetable for the cohort's students alone. Classes are counted, and
+  // listed when they lack a teacher or room, if they run in the cohort's slots and are of
+  // subjects it offers.
+  pub fn cohort_report(&self, cohort_name: impl Into<String>) -> Option<SortReport> {
+    let cohort = self.get_cohort(cohort_name)?;
+    let weak_cohort = Arc::downgrade(&cohort);
+    let student_list: Vec<Arc<Student>> = self
+      .student_list
+      .iter()
+      .filter(|student| {
+        student
+          .cohort
+          .as_ref()
+          .is_some_and(|x| x.ptr_eq(&weak_cohort))
+      })
+      .cloned()
+      .collect();
+    let in_cohort =
+      |slot: usize, class: &Class| cohort.uses_slot(slot) && cohort.offers(&class.subject);
+
+    let mut report = self.report(&student_list);
+    report
+      .untaught_class_list
+      .retain(|(slot, class)| in_cohort(*slot, class));
+    report
+      .unroomed_class_list
+      .retain(|(slot, class)| in_cohort(*slot, class));
+    (report.classes_created, report.classes_removed) = (0, 0);
+    for (slot_index, slot) in self.timetable.slot_list.iter().enumerate() {
+      for class in &slot.lock().unwrap().class_list {
+        if in_cohort(slot_index, class) {
+          report.classes_created += 1;
+          if class.removed {
+            report.classes_removed += 1;
+          }
+        }
+      }
+    }
+    Some(report)
+  }
+}
+
+fn check_slot_demand(student: &Student, slot_count: usize) -> Result<()> {
+  let slot_count = student.slot_count(slot_count);
+  if student.slot_demand() > slot_count {
+    return Err(Error::Generic(format!(
+      "Student (name: {} {}, id: {}) has subjects taking {} slots but there are only {} slots",
+      student.first_name,
+      student.last_name,
+      student.id,
+      student.slot_demand(),
+      slot_count
+    )));
+  }
+  Ok(())
+}
+
+// Fails listing every broken rule, so that they can all be fixed at once
+fn violation_check(violation_list: &[RuleViolation]) -> Result<()> {
+  if violation_list.is_empty() {
+    return Ok(());
+  }
+  let message_list: Vec<String> = violation_list.iter().map(|x| x.to_string()).collect();
+  Err(Error::Generic(message_list.join("; ")))
 }
 
 #[cfg(test)]
@@ -331,10 +1295,10 @@ mod tests {
 
     let department = high_school
       .subject_list
-      .get(0)
+      .first()
       .unwrap()
       .department_list
-      .get(0)
+      .first()
       .unwrap()
       .upgrade()
       .unwrap()
@@ -365,10 +1329,10 @@ mod tests {
 
     let department = high_school
       .subject_list
-      .get(0)
+      .first()
       .unwrap()
       .department_list
-      .get(0)
+      .first()
       .unwrap()
       .upgrade()
       .unwrap()
@@ -483,6 +1447,37 @@ mod tests {
       .unwrap();
   }
 
+  #[test]
+  #[should_panic]
+  fn new_teacher_same_id() {
+    let mut high_school = Schedule::new();
+
+    high_school.new_department("Math", 30, 35, 10).unwrap();
+
+    high_school
+      .new_teacher("T1", "Teacher One", Vec::<String>::new(), vec!["Math"], 5)
+      .unwrap();
+    high_school
+      .new_teacher("T1", "Teacher One", Vec::<String>::new(), vec!["Math"], 5)
+      .unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn new_teacher_unknown_subject() {
+    let mut high_school = Schedule::new();
+
+    high_school
+      .new_teacher(
+        "T1",
+        "Teacher One",
+        vec!["Calculus"],
+        Vec::<String>::new(),
+        5,
+      )
+      .unwrap();
+  }
+
   #[test]
   fn new_student_no_subjects() {
     let mut high_school = Schedule::new();
@@ -501,4 +1496,65 @@ mod tests {
       .new_subject("Maths", Vec::<String>::new())
       .unwrap();
   }
+
+  #[test]
+  fn set_slot_count() {
+    let mut high_school = Schedule::with_slot_count(3);
+
+    high_school.new_department("Math", 1, 35, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Math"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus"])
+      .unwrap();
+
+    high_school.sort().unwrap();
+    high_school.timetable.clear();
+    assert_eq!(high_school.slot_count(), 3);
+    high_school.set_slot_count(6).unwrap();
+    high_school.sort().unwrap();
+    assert_eq!(high_school.slot_count(), 6);
+  }
+
+  #[test]
+  #[should_panic]
+  fn new_student_more_subjects_than_slots() {
+    let mut high_school = Schedule::with_slot_count(1);
+
+    high_school.new_department("Math", 30, 35, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Math"]).unwrap();
+    high_school.new_subject("Statistics", vec!["Math"]).unwrap();
+
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Statistics"])
+      .unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn set_slot_count_below_student_subjects() {
+    let mut high_school = Schedule::new();
+
+    high_school.new_department("Math", 30, 35, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Math"]).unwrap();
+    high_school.new_subject("Statistics", vec!["Math"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Statistics"])
+      .unwrap();
+
+    high_school.set_slot_count(1).unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn new_student_compulsory_not_chosen() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 1, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject("Statistics", vec!["Maths"])
+      .unwrap();
+    high_school
+      .new_student_with_compulsory("Person", "One", "1", vec!["Calculus"], vec!["Statistics"])
+      .unwrap();
+  }
 }
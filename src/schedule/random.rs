@@ -1,14 +1,52 @@
 use std::sync::Arc;
 
-use rand::seq::SliceRandom;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
-use crate::{Department, Schedule, StudentBuilder, SubjectBuilder};
+use crate::{prelude::*, Department, Schedule, StudentBuilder, SubjectBuilder, DEFAULT_SLOT_COUNT};
 
+// Generates a school from a random seed, which is kept on the schedule so the run can be
+// replayed with random_schedule_from_seed
 pub fn random_schedule(
   student_count: usize,
   subject_count: usize,
   classroom_count: usize,
   department_count: usize,
+) -> Schedule {
+  random_schedule_from_seed(
+    rand::thread_rng().gen(),
+    student_count,
+    subject_count,
+    classroom_count,
+    department_count,
+  )
+}
+
+// The same seed always generates the same school, and the solvers used to sort it make
+// their random choices from the same seed
+pub fn random_schedule_from_seed(
+  seed: u64,
+  student_count: usize,
+  subject_count: usize,
+  classroom_count: usize,
+  department_count: usize,
+) -> Schedule {
+  let mut random_schedule = random_schedule_with_rng(
+    &mut StdRng::seed_from_u64(seed),
+    student_count,
+    subject_count,
+    classroom_count,
+    department_count,
+  );
+  random_schedule.seed = Some(seed);
+  random_schedule
+}
+
+pub fn random_schedule_with_rng(
+  rng: &mut impl Rng,
+  student_count: usize,
+  subject_count: usize,
+  classroom_count: usize,
+  department_count: usize,
 ) -> Schedule {
   let mut random_schedule = Schedule::new();
 
@@ -28,9 +66,9 @@ pub fn random_schedule(
     let mut subject_builder = SubjectBuilder::new().name(format!("Subject {}", i));
     for department in random_schedule
       .department_list
-      .choose_multiple(&mut rand::thread_rng(), 2)
+      .choose_multiple(rng, 2)
     {
-      subject_builder = subject_builder.department(Arc::downgrade(&department));
+      subject_builder = subject_builder.department(Arc::downgrade(department));
     }
     random_schedule
       .add_subject(subject_builder.build().unwrap())
@@ -46,12 +84,453 @@ pub fn random_schedule(
       .unwrap();
     random_schedule
       .subject_list
-      .choose_multiple(&mut rand::thread_rng(), 5)
+      .choose_multiple(rng, 5)
       .for_each(|s| {
-        student.subject_list.push(Arc::downgrade(&s));
+        student.subject_list.push(Arc::downgrade(s));
       });
     random_schedule.add_student(student).unwrap();
   });
 
   random_schedule
 }
+
+#[derive(Debug, Clone)]
+pub struct DepartmentConfig {
+  pub name: String,
+  pub min_class_size: usize,
+  pub max_class_size: usize,
+  pub class_count: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubjectConfig {
+  pub name: String,
+  pub department_list: Vec<String>,
+  // Chance, from 0 to 1, that a student in the year level chooses the subject
+  pub popularity: f64,
+}
+
+// Students who choose the subject also choose the implied subject with the given chance
+#[derive(Debug, Clone)]
+pub struct CorrelationConfig {
+  pub subject: String,
+  pub implies: String,
+  pub probability: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct YearLevelConfig {
+  // Also the name of the year level's cohort
+  pub name: String,
+  pub student_count: usize,
+  pub min_subject_count: usize,
+  pub max_subject_count: usize,
+  // Subjects with the same name in different year levels are shared between them
+  pub subject_list: Vec<SubjectConfig>,
+  pub correlation_list: Vec<CorrelationConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+  pub slot_count: usize,
+  pub department_list: Vec<DepartmentConfig>,
+  pub year_level_list: Vec<YearLevelConfig>,
+}
+
+impl Default for GeneratorConfig {
+  fn default() -> Self {
+    Self {
+      slot_count: DEFAULT_SLOT_COUNT,
+      department_list: Vec::new(),
+      year_level_list: Vec::new(),
+    }
+  }
+}
+
+pub fn generate_schedule(config: &GeneratorConfig, seed: u64) -> Result<Schedule> {
+  let mut schedule = generate_schedule_with_rng(config, &mut StdRng::seed_from_u64(seed))?;
+  schedule.seed = Some(seed);
+  Ok(schedule)
+}
+
+pub fn generate_schedule_with_rng(
+  config: &GeneratorConfig,
+  rng: &mut impl Rng,
+) -> Result<Schedule> {
+  let mut schedule = Schedule::with_slot_count(config.slot_count);
+
+  for department in &config.department_list {
+    schedule.new_department(
+      &department.name,
+      department.min_class_size,
+      department.max_class_size,
+      department.class_count,
+    )?;
+  }
+
+  let mut student_id = 0;
+  for year_level in &config.year_level_list {
+    if year_level.max_subject_count > config.slot_count {
+      return Err(Error::Generic(format!(
+        "Year level ({}) has a maximum subject count above the {} slots",
+        year_level.name, config.slot_count
+      )));
+    }
+    if year_level.min_subject_count > year_level.max_subject_count {
+      return Err(Error::Generic(format!(
+        "Year level ({}) has a minimum subject count above its maximum",
+        year_level.name
+      )));
+    }
+    if year_level.min_subject_count > year_level.subject_list.len() {
+      return Err(Error::Generic(format!(
+        "Year level ({}) offers fewer subjects than its minimum subject count",
+        year_level.name
+      )));
+    }
+    for subject in &year_level.subject_list {
+      if !(0.0..=1.0).contains(&subject.popularity) {
+        return Err(Error::Generic(format!(
+          "Subject ({}) has a popularity outside of 0 to 1",
+          subject.name
+        )));
+      }
+      if !schedule.subject_name_in_use(&subject.name) {
+        schedule.new_subject(&subject.name, subject.department_list.clone())?;
+      }
+    }
+    for correlation in &year_level.correlation_list {
+      if !(0.0..=1.0).contains(&correlation.probability) {
+        return Err(Error::Generic(format!(
+          "Correlation from subject ({}) to subject ({}) has a probability outside of 0 to 1",
+          correlation.subject, correlation.implies
+        )));
+      }
+      for name in [&correlation.subject, &correlation.implies] {
+        if !year_level.subject_list.iter().any(|x| &x.name == name) {
+          return Err(Error::Generic(format!(
+            "Correlated subject ({}) is not offered to year level ({})",
+            name, year_level.name
+          )));
+        }
+      }
+    }
+
+    schedule.new_cohort(
+      &year_level.name,
+      year_level
+        .subject_list
+        .iter()
+        .map(|x| x.name.clone())
+        .collect(),
+      (0..config.slot_count).collect(),
+    )?;
+
+    for _ in 0..year_level.student_count {
+      let subject_list = choose_subjects(year_level, rng);
+      schedule.new_student_in_cohort(
+        "Person",
+        student_id.to_string(),
+        student_id.to_string(),
+        &year_level.name,
+        subject_list,
+      )?;
+      student_id += 1;
+    }
+  }
+
+  Ok(schedule)
+}
+
+fn choose_subjects(year_level: &YearLevelConfig, rng: &mut impl Rng) -> Vec<String> {
+  let subject_count = rng.gen_range(year_level.min_subject_count..=year_level.max_subject_count);
+  let subject_count = subject_count.min(year_level.subject_list.len());
+
+  // Every subject is first chosen independently according to its popularity, and kept in
+  // order of priority, which starts out as that popularity
+  let mut chosen: Vec<(&SubjectConfig, f64)> = year_level
+    .subject_list
+    .iter()
+    .filter(|subject| rng.gen_bool(subject.popularity))
+    .map(|subject| (subject, subject.popularity))
+    .collect();
+  for correlation in &year_level.correlation_list {
+    let Some(priority) = chosen
+      .iter()
+      .find(|(x, _)| x.name == correlation.subject)
+      .map(|(_, priority)| *priority)
+    else {
+      continue;
+    };
+    if !rng.gen_bool(correlation.probability) {
+      continue;
+    }
+    // An implied subject is always kept ahead of the subject that implied it
+    let priority = priority + f64::EPSILON;
+    match chosen
+      .iter_mut()
+      .find(|(x, _)| x.name == correlation.implies)
+    {
+      Some((_, implied_priority)) => *implied_priority = implied_priority.max(priority),
+      None => chosen.push((
+        year_level
+          .subject_list
+          .iter()
+          .find(|x| x.name == correlation.implies)
+          .unwrap(),
+        priority,
+      )),
+    }
+  }
+
+  // Then the lowest priority choices are dropped, or popular subjects added, to fit the count
+  chosen.shuffle(rng);
+  chosen.sort_by(|a, b| b.1.total_cmp(&a.1));
+  chosen.truncate(subject_count);
+  let mut skipped: Vec<&str> = Vec::new();
+  while chosen.len() < subject_count {
+    let remaining: Vec<&SubjectConfig> = year_level
+      .subject_list
+      .iter()
+      .filter(|subject| {
+        !chosen.iter().any(|(x, _)| x.name == subject.name)
+          && !skipped.contains(&subject.name.as_str())
+      })
+      .collect();
+    let Ok(&subject) = remaining.choose_weighted(rng, |x| x.popularity + f64::EPSILON) else {
+      break;
+    };
+    // Added subjects bring the subjects they imply with them, if there is room for both
+    let mut added = vec![subject];
+    for correlation in &year_level.correlation_list {
+      if correlation.subject == subject.name
+        && !chosen.iter().any(|(x, _)| x.name == correlation.implies)
+        && !added.iter().any(|x| x.name == correlation.implies)
+        && rng.gen_bool(correlation.probability)
+      {
+        added.push(
+          year_level
+            .subject_list
+            .iter()
+            .find(|x| x.name == correlation.implies)
+            .unwrap(),
+        );
+      }
+    }
+    if chosen.len() + added.len() > subject_count {
+      skipped.push(&subject.name);
+      continue;
+    }
+    chosen.extend(added.into_iter().map(|x| (x, x.popularity)));
+  }
+  // Below the minimum, subjects skipped for lack of room for what they imply are taken alone
+  for name in skipped {
+    if chosen.len() >= year_level.min_subject_count {
+      break;
+    }
+    let subject = year_level
+      .subject_list
+      .iter()
+      .find(|x| x.name == name)
+      .unwrap();
+    chosen.push((subject, subject.popularity));
+  }
+
+  chosen.into_iter().map(|(x, _)| x.name.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn subject_names(schedule: &Schedule) -> Vec<Vec<String>> {
+    schedule
+      .student_list
+      .iter()
+      .map(|student| {
+        student
+          .subject_list
+          .iter()
+          .map(|subject| subject.upgrade().unwrap().name.clone())
+          .collect()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_same_seed_same_schedule() {
+    let mut first = random_schedule_from_seed(1234, 200, 12, 40, 8);
+    let mut second = random_schedule_from_seed(1234, 200, 12, 40, 8);
+    assert_eq!(first.seed, Some(1234));
+    assert_eq!(subject_names(&first), subject_names(&second));
+
+    let first_report = first
+      .sort_with(&mut crate::MultiStartSolver::new(3))
+      .unwrap();
+    let second_report = second
+      .sort_with(&mut crate::MultiStartSolver::new(3))
+      .unwrap();
+    assert_eq!(first_report.score.total, second_report.score.total);
+  }
+
+  fn test_config() -> GeneratorConfig {
+    let subject = |name: &str, department: &str, popularity: f64| SubjectConfig {
+      name: name.to_string(),
+      department_list: vec![department.to_string()],
+      popularity,
+    };
+    GeneratorConfig {
+      slot_count: 6,
+      department_list: vec![
+        DepartmentConfig {
+          name: "English".to_string(),
+          min_class_size: 15,
+          max_class_size: 30,
+          class_count: 6,
+        },
+        DepartmentConfig {
+          name: "Science".to_string(),
+          min_class_size: 10,
+          max_class_size: 25,
+          class_count: 4,
+        },
+        DepartmentConfig {
+          name: "Maths".to_string(),
+          min_class_size: 10,
+          max_class_size: 30,
+          class_count: 4,
+        },
+        DepartmentConfig {
+          name: "Arts".to_string(),
+          min_class_size: 8,
+          max_class_size: 20,
+          class_count: 3,
+        },
+      ],
+      year_level_list: vec![YearLevelConfig {
+        name: "Year 12".to_string(),
+        student_count: 400,
+        min_subject_count: 4,
+        max_subject_count: 6,
+        subject_list: vec![
+          subject("English", "English", 0.9),
+          subject("Physics", "Science", 0.3),
+          subject("Chemistry", "Science", 0.3),
+          subject("Calculus", "Maths", 0.2),
+          subject("Statistics", "Maths", 0.5),
+          subject("Painting", "Arts", 0.05),
+          subject("Design", "Arts", 0.2),
+        ],
+        correlation_list: vec![CorrelationConfig {
+          subject: "Physics".to_string(),
+          implies: "Calculus".to_string(),
+          probability: 1.0,
+        }],
+      }],
+    }
+  }
+
+  fn chosen_by(schedule: &Schedule, name: &str) -> usize {
+    subject_names(schedule)
+      .iter()
+      .filter(|subject_list| subject_list.iter().any(|x| x == name))
+      .count()
+  }
+
+  #[test]
+  fn test_generate_schedule() {
+    let schedule = generate_schedule(&test_config(), 7).unwrap();
+    assert_eq!(schedule.seed, Some(7));
+    assert_eq!(schedule.student_list.len(), 400);
+    for subject_list in subject_names(&schedule) {
+      assert!((4..=6).contains(&subject_list.len()));
+      if subject_list.iter().any(|x| x == "Physics") {
+        assert!(subject_list.iter().any(|x| x == "Calculus"));
+      }
+    }
+    assert!(chosen_by(&schedule, "English") > 300);
+    assert!(chosen_by(&schedule, "Painting") < chosen_by(&schedule, "Design"));
+
+    let again = generate_schedule(&test_config(), 7).unwrap();
+    assert_eq!(subject_names(&schedule), subject_names(&again));
+  }
+
+  #[test]
+  fn test_generate_schedule_unknown_department() {
+    let mut config = test_config();
+    config.year_level_list[0].subject_list[0].department_list = vec!["Music".to_string()];
+    assert!(generate_schedule(&config, 7).is_err());
+  }
+
+  #[test]
+  fn test_generate_schedule_bad_correlation() {
+    let mut config = test_config();
+    let year_level = config
+      .year_level_list
+      .iter_mut()
+      .find(|x| !x.correlation_list.is_empty())
+      .unwrap();
+    year_level.correlation_list[0].probability = 1.5;
+    assert!(generate_schedule(&config, 7).is_err());
+  }
+
+  #[test]
+  fn test_generate_schedule_keeps_min_subject_count() {
+    let subject = |name: &str| SubjectConfig {
+      name: name.to_string(),
+      department_list: vec!["Maths".to_string()],
+      popularity: 0.5,
+    };
+    let correlation = |subject: &str, implies: &str| CorrelationConfig {
+      subject: subject.to_string(),
+      implies: implies.to_string(),
+      probability: 1.0,
+    };
+    // Subjects come in pairs, so a third subject never has room for the one it implies
+    let config = GeneratorConfig {
+      slot_count: 3,
+      department_list: vec![DepartmentConfig {
+        name: "Maths".to_string(),
+        min_class_size: 1,
+        max_class_size: 30,
+        class_count: 4,
+      }],
+      year_level_list: vec![YearLevelConfig {
+        name: "Year 12".to_string(),
+        student_count: 20,
+        min_subject_count: 3,
+        max_subject_count: 3,
+        subject_list: vec![
+          subject("Calculus"),
+          subject("Statistics"),
+          subject("Algebra"),
+          subject("Geometry"),
+        ],
+        correlation_list: vec![
+          correlation("Calculus", "Statistics"),
+          correlation("Statistics", "Calculus"),
+          correlation("Algebra", "Geometry"),
+          correlation("Geometry", "Algebra"),
+        ],
+      }],
+    };
+    for seed in 0..50 {
+      let schedule = generate_schedule(&config, seed).unwrap();
+      for subject_list in subject_names(&schedule) {
+        assert_eq!(subject_list.len(), 3);
+      }
+    }
+
+    let mut config = config;
+    config.year_level_list[0].subject_list.truncate(2);
+    config.year_level_list[0].correlation_list.truncate(2);
+    assert!(generate_schedule(&config, 7).is_err());
+  }
+
+  #[test]
+  fn test_different_seed_different_schedule() {
+    let first = random_schedule_from_seed(1, 200, 12, 40, 8);
+    let second = random_schedule_from_seed(2, 200, 12, 40, 8);
+    assert_ne!(subject_names(&first), subject_names(&second));
+  }
+}
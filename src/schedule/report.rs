@@ -0,0 +1,316 @@
+use crate::{timetable::Timetable, AnnealReport, Class, Score, Student, Subject};
+use std::{
+  fmt,
+  sync::{Arc, Weak},
+};
+
+#[derive(Debug, Clone)]
+pub struct StudentReport {
+  pub student: Weak<Student>,
+  // Requested subjects the student has a class for
+  pub placed: Vec<Weak<Subject>>,
+  // Requested subjects whose class was removed for being too small
+  pub dropped: Vec<Weak<Subject>>,
+  // Requested subjects the student was never put into a class for
+  pub unplaced: Vec<Weak<Subject>>,
+  // Reserve subjects the student was given in place of missing choices
+  pub reserve_list: Vec<Weak<Subject>>,
+  // Nearby subject combinations that would fit, for students a blocking plan could not hold
+  pub alternative_list: Vec<Vec<Weak<Subject>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SortReport {
+  pub student_report_list: Vec<StudentReport>,
+  pub classes_created: usize,
+  pub classes_removed: usize,
+  // Classes no qualified teacher could take, with the index of their slot
+  pub untaught_class_list: Vec<(usize, Class)>,
+  // Classes no room could hold, with the index of their slot
+  pub unroomed_class_list: Vec<(usize, Class)>,
+  pub score: Score,
+  // Set when the solver finished with a simulated annealing pass
+  pub anneal_report: Option<AnnealReport>,
+}
+
+impl StudentReport {
+  pub fn new(student: &Arc<Student>, timetable: &Timetable) -> Self {
+    let mut report = Self {
+      student: Arc::downgrade(student),
+      placed: Vec::new(),
+      dropped: Vec::new(),
+      unplaced: Vec::new(),
+      reserve_list: Vec::new(),
+      alternative_list: Vec::new(),
+    };
+    report.reserve_list = student
+      .reserve_list
+      .iter()
+      .filter(|reserve| {
+        timetable.slot_list.iter().any(|slot| {
+          slot.lock().unwrap().class_list.iter().any(|class| {
+            !class.removed
+              && class.subject.ptr_eq(reserve)
+              && class.contains(Arc::downgrade(student))
+          })
+        })
+      })
+      .map(Weak::clone)
+      .collect();
+    for subject in &student.subject_list {
+      let (mut placed, mut dropped) = (false, false);
+      for slot in &timetable.slot_list {
+        for class in &slot.lock().unwrap().class_list {
+          if !class.subject.ptr_eq(subject) || !class.contains(Arc::downgrade(student)) {
+            continue;
+          }
+          if class.removed {
+            dropped = true;
+          } else {
+            placed = true;
+          }
+        }
+      }
+      if placed {
+        report.placed.push(Weak::clone(subject));
+      } else if dropped {
+        report.dropped.push(Weak::clone(subject));
+      } else {
+        report.unplaced.push(Weak::clone(subject));
+      }
+    }
+    report
+  }
+  pub fn requested_count(&self) -> usize {
+    self.placed.len() + self.dropped.len() + self.unplaced.len()
+  }
+  pub fn is_fully_placed(&self) -> bool {
+    self.dropped.is_empty() && self.unplaced.is_empty()
+  }
+  // Whether each of the student's first choice_count choices, or all of them if they made
+  // fewer, was placed
+  pub fn has_first_choices(&self, choice_count: usize) -> bool {
+    let student = self.student.upgrade().unwrap();
+    student
+      .subject_list
+      .iter()
+      .take(choice_count)
+      .all(|subject| self.placed.iter().any(|x| x.ptr_eq(subject)))
+  }
+}
+
+impl SortReport {
+  pub fn new(student_list: &[Arc<Student>], timetable: &Timetable) -> Self {
+    let mut report = Self {
+      student_report_list: student_list
+        .iter()
+        .map(|student| StudentReport::new(student, timetable))
+        .collect(),
+      ..Default::default()
+    };
+    for slot in &timetable.slot_list {
+      for class in &slot.lock().unwrap().class_list {
+        report.classes_created += 1;
+        if class.removed {
+          report.classes_removed += 1;
+        }
+      }
+    }
+    report
+  }
+  pub fn requested_count(&self) -> usize {
+    self
+      .student_report_list
+      .iter()
+      .map(|x| x.requested_count())
+      .sum()
+  }
+  pub fn placed_count(&self) -> usize {
+    self.student_report_list.iter().map(|x| x.placed.len()).sum()
+  }
+  pub fn dropped_count(&self) -> usize {
+    self.student_report_list.iter().map(|x| x.dropped.len()).sum()
+  }
+  pub fn unplaced_count(&self) -> usize {
+    self.student_report_list.iter().map(|x| x.unplaced.len()).sum()
+  }
+  // Fraction of all requested subjects that were placed, 1.0 when nothing was requested
+  pub fn placement_rate(&self) -> f64 {
+    let requested_count = self.requested_count();
+    if requested_count == 0 {
+      return 1.0;
+    }
+    self.placed_count() as f64 / requested_count as f64
+  }
+  pub fn fully_placed_student_count(&self) -> usize {
+    self
+      .student_report_list
+      .iter()
+      .filter(|x| x.is_fully_placed())
+      .count()
+  }
+  // Students who were placed in each of their first choice_count choices
+  pub fn first_choices_count(&self, choice_count: usize) -> usize {
+    self
+      .student_report_list
+      .iter()
+      .filter(|x| x.has_first_choices(choice_count))
+      .count()
+  }
+  pub fn reserve_count(&self) -> usize {
+    self
+      .student_report_list
+      .iter()
+      .map(|x| x.reserve_list.len())
+      .sum()
+  }
+  pub fn get_student_report(&self, student_id: impl Into<String>) -> Option<&StudentReport> {
+    let student_id = student_id.into();
+    self
+      .student_report_list
+      .iter()
+      .find(|x| x.student.upgrade().unwrap().id == student_id)
+  }
+}
+
+impl fmt::Display for SortReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "Placed {} of {} requested subjects ({:.1}%)",
+      self.placed_count(),
+      self.requested_count(),
+      self.placement_rate() * 100.0
+    )?;
+    writeln!(
+      f,
+      "  Dropped by small class removal: {}",
+      self.dropped_count()
+    )?;
+    writeln!(f, "  Never placed: {}", self.unplaced_count())?;
+    writeln!(
+      f,
+      "  Students with every subject: {} of {}",
+      self.fully_placed_student_count(),
+      self.student_report_list.len()
+    )?;
+    let max_choice_count = self
+      .student_report_list
+      .iter()
+      .map(|x| x.requested_count())
+      .max()
+      .unwrap_or(0);
+    if max_choice_count > 0 {
+      write!(f, "  Students with their first N choices:")?;
+      for choice_count in 1..=max_choice_count {
+        write!(
+          f,
+          "{} {}: {}",
+          if choice_count > 1 { "," } else { "" },
+          choice_count,
+          self.first_choices_count(choice_count)
+        )?;
+      }
+      writeln!(f)?;
+    }
+    if self.reserve_count() > 0 {
+      writeln!(f, "  Reserves taken: {}", self.reserve_count())?;
+    }
+    writeln!(
+      f,
+      "  Classes created: {}, removed: {}",
+      self.classes_created, self.classes_removed
+    )?;
+    let alternative_count = self
+      .student_report_list
+      .iter()
+      .filter(|x| !x.alternative_list.is_empty())
+      .count();
+    if alternative_count > 0 {
+      writeln!(
+        f,
+        "  Students offered other combinations: {}",
+        alternative_count
+      )?;
+    }
+    if !self.untaught_class_list.is_empty() {
+      writeln!(
+        f,
+        "  Classes without a teacher: {}",
+        self.untaught_class_list.len()
+      )?;
+    }
+    if !self.unroomed_class_list.is_empty() {
+      writeln!(
+        f,
+        "  Classes without a room: {}",
+        self.unroomed_class_list.len()
+      )?;
+    }
+    if let Some(anneal_report) = &self.anneal_report {
+      writeln!(f, "  {}", anneal_report)?;
+    }
+    write!(f, "  {}", self.score)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Schedule;
+
+  #[test]
+  fn test_sort_report() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 2, 30, 10).unwrap();
+    high_school.new_department("Art", 2, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Painting"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Calculus"])
+      .unwrap();
+
+    let report = high_school.sort().unwrap();
+
+    assert_eq!(report.requested_count(), 3);
+    assert_eq!(report.placed_count(), 2);
+    assert_eq!(report.dropped_count(), 1);
+    assert_eq!(report.unplaced_count(), 0);
+    assert_eq!(report.classes_created, 2);
+    assert_eq!(report.classes_removed, 1);
+    let student_report = report.get_student_report("1").unwrap();
+    assert!(student_report.dropped[0].ptr_eq(&Arc::downgrade(
+      &high_school.get_subject("Painting").unwrap()
+    )));
+  }
+
+  #[test]
+  fn test_first_choices() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 2, 30, 10).unwrap();
+    high_school.new_department("Art", 3, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Painting"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Painting", "Calculus"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Three", "3", vec!["Calculus"])
+      .unwrap();
+
+    let report = high_school.sort().unwrap();
+
+    // Painting is too small to run, which only costs the second student their first choice
+    assert_eq!(report.first_choices_count(1), 2);
+    assert_eq!(report.first_choices_count(2), 1);
+    assert!(report
+      .to_string()
+      .contains("Students with their first N choices: 1: 2, 2: 1"));
+  }
+}
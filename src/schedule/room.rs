@@ -0,0 +1,230 @@
+use crate::{prelude::*, timetable::Class, Department, Subject};
+use std::sync::Weak;
+
+#[derive(Default)]
+pub struct RoomBuilder {
+  name: Option<String>,
+  capacity: Option<usize>,
+  department_list: Vec<Weak<Department>>,
+  feature_list: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Room {
+  pub name: String,
+  // Number of seats
+  pub capacity: usize,
+  // Departments whose classes can be held in the room
+  pub department_list: Vec<Weak<Department>>,
+  // Equipment the room has, such as "lab", "computers" or "gym"
+  pub feature_list: Vec<String>,
+}
+
+impl Room {
+  pub fn belongs_to(&self, department: &Weak<Department>) -> bool {
+    self.department_list.iter().any(|x| x.ptr_eq(department))
+  }
+  // Whether the class's department uses the room, it has a seat for every student and it has
+  // everything the subject needs
+  pub fn can_hold(&self, class: &Class) -> bool {
+    self.can_seat(&class.department, &class.subject, class.student_list.len())
+  }
+  // Whether a class of the size, run by the department, could be held in the room
+  pub fn can_seat(
+    &self,
+    department: &Weak<Department>,
+    subject: &Weak<Subject>,
+    size: usize,
+  ) -> bool {
+    self.belongs_to(department)
+      && size <= self.capacity
+      && subject
+        .upgrade()
+        .unwrap()
+        .feature_list
+        .iter()
+        .all(|feature| self.feature_list.contains(feature))
+  }
+}
+
+// Whether each department can run its count of classes at once with every class in a room of
+// its own. Departments are given by index, and each room by the indices of the departments that
+// use it, so a room shared between departments is only counted once.
+pub(crate) fn rooms_fit(room_department_list: &[Vec<usize>], class_count_list: &[usize]) -> bool {
+  let class_list: Vec<usize> = class_count_list
+    .iter()
+    .enumerate()
+    .flat_map(|(department, &count)| std::iter::repeat_n(department, count))
+    .collect();
+  if class_list.len() > room_department_list.len() {
+    return false;
+  }
+  // Matches one class at a time, moving earlier classes into other rooms to make way
+  fn assign(
+    class: usize,
+    class_list: &[usize],
+    room_department_list: &[Vec<usize>],
+    room_class: &mut [Option<usize>],
+    visited: &mut [bool],
+  ) -> bool {
+    for room in 0..room_department_list.len() {
+      if visited[room] || !room_department_list[room].contains(&class_list[class]) {
+        continue;
+      }
+      visited[room] = true;
+      if room_class[room]
+        .is_none_or(|other| assign(other, class_list, room_department_list, room_class, visited))
+      {
+        room_class[room] = Some(class);
+        return true;
+      }
+    }
+    false
+  }
+  let mut room_class = vec![None; room_department_list.len()];
+  (0..class_list.len()).all(|class| {
+    let mut visited = vec![false; room_department_list.len()];
+    assign(
+      class,
+      &class_list,
+      room_department_list,
+      &mut room_class,
+      &mut visited,
+    )
+  })
+}
+
+impl RoomBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn name(self, new_name: impl Into<String>) -> Self {
+    Self {
+      name: Some(new_name.into()),
+      ..self
+    }
+  }
+  pub fn capacity(self, new_capacity: usize) -> Self {
+    Self {
+      capacity: Some(new_capacity),
+      ..self
+    }
+  }
+  pub fn department(self, new_department: Weak<Department>) -> Self {
+    let mut new_department_list = self.department_list;
+    new_department_list.push(new_department);
+    Self {
+      department_list: new_department_list,
+      ..self
+    }
+  }
+  pub fn feature(self, new_feature: impl Into<String>) -> Self {
+    let mut new_feature_list = self.feature_list;
+    new_feature_list.push(new_feature.into());
+    Self {
+      feature_list: new_feature_list,
+      ..self
+    }
+  }
+  pub fn build(self) -> Result<Room> {
+    let Some(name) = self.name else {
+      return Err(Error::Generic("Cannot build room without name".to_string()));
+    };
+    let Some(capacity) = self.capacity else {
+      return Err(Error::Generic(
+        "Cannot build room without capacity".to_string(),
+      ));
+    };
+    if self.department_list.is_empty() {
+      return Err(Error::Generic(
+        "Cannot build room without any departments".to_string(),
+      ));
+    }
+    Ok(Room {
+      name,
+      capacity,
+      department_list: self.department_list,
+      feature_list: self.feature_list,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SubjectBuilder;
+  use std::sync::Arc;
+
+  #[test]
+  #[should_panic]
+  fn create_room_missing_department() {
+    let _room = RoomBuilder::new().name("S1").capacity(30).build().unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn create_room_missing_capacity() {
+    let science_department = Arc::new(Department {
+      name: "Science Department".to_string(),
+      min_class_size: 20,
+      max_class_size: 30,
+      class_count: 20,
+    });
+    let _room = RoomBuilder::new()
+      .name("S1")
+      .department(Arc::downgrade(&science_department))
+      .build()
+      .unwrap();
+  }
+
+  #[test]
+  fn shared_rooms_fit() {
+    // Room 0 is Maths only, room 1 is shared between Maths and Science
+    let room_department_list = vec![vec![0], vec![0, 1]];
+    assert!(rooms_fit(&room_department_list, &[1, 1]));
+    assert!(rooms_fit(&room_department_list, &[2, 0]));
+    assert!(!rooms_fit(&room_department_list, &[2, 1]));
+    assert!(!rooms_fit(&room_department_list, &[0, 2]));
+  }
+
+  #[test]
+  fn room_can_hold() {
+    let science_department = Arc::new(Department {
+      name: "Science Department".to_string(),
+      min_class_size: 1,
+      max_class_size: 30,
+      class_count: 20,
+    });
+    let chemistry = Arc::new(
+      SubjectBuilder::new()
+        .name("Chemistry")
+        .department(Arc::downgrade(&science_department))
+        .feature("lab")
+        .build()
+        .unwrap(),
+    );
+    let lab = RoomBuilder::new()
+      .name("S1")
+      .capacity(1)
+      .department(Arc::downgrade(&science_department))
+      .feature("lab")
+      .build()
+      .unwrap();
+    let classroom = RoomBuilder::new()
+      .name("S2")
+      .capacity(30)
+      .department(Arc::downgrade(&science_department))
+      .build()
+      .unwrap();
+
+    let mut class = Class::new(
+      Arc::downgrade(&chemistry),
+      Arc::downgrade(&science_department),
+      Vec::new(),
+    );
+    assert!(lab.can_hold(&class));
+    assert!(!classroom.can_hold(&class));
+    class.student_list = vec![Weak::new(), Weak::new()];
+    assert!(!lab.can_hold(&class));
+  }
+}
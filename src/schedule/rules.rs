@@ -0,0 +1,223 @@
+//! Rules on which combinations of subjects a student may take
+
+use crate::{Student, Subject};
+use std::{fmt, sync::Weak};
+
+// Rules for a new subject, naming the subjects they refer to
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SubjectRules {
+  // Subjects a student must have completed, which need not be offered this year
+  pub prerequisite_list: Vec<String>,
+  // Subjects, already in the schedule, a student must take alongside the new one
+  pub corequisite_list: Vec<String>,
+  // Subjects, already in the schedule, a student cannot take alongside the new one
+  pub exclusion_list: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum RuleBreach {
+  // The named subject has not been completed
+  MissingPrerequisite(String),
+  // The named subject is not being taken
+  MissingCorequisite(String),
+  // The named subject is also being taken
+  Excluded(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuleViolation {
+  pub student_id: String,
+  pub subject: String,
+  pub breach: RuleBreach,
+}
+
+impl fmt::Display for RuleViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Student ({}) takes {} ", self.student_id, self.subject)?;
+    match &self.breach {
+      RuleBreach::MissingPrerequisite(other) => write!(f, "without having completed {}", other),
+      RuleBreach::MissingCorequisite(other) => write!(f, "without also taking {}", other),
+      RuleBreach::Excluded(other) => write!(f, "alongside {}, which it excludes", other),
+    }
+  }
+}
+
+// Every rule of the student's chosen subjects that their choices break. An exclusion between
+// two subjects is reported once, against the subject that declared it.
+pub fn rule_violations(student: &Student) -> Vec<RuleViolation> {
+  combination_violations(student, &student.subject_list)
+}
+
+// Rules the student would break by taking the combination of subjects instead of their own
+pub fn combination_violations(
+  student: &Student,
+  subject_list: &[Weak<Subject>],
+) -> Vec<RuleViolation> {
+  let takes = |subject: &Weak<Subject>| subject_list.iter().any(|x| x.ptr_eq(subject));
+  let mut violation_list = Vec::new();
+  for subject in subject_list {
+    let subject = subject.upgrade().unwrap();
+    let mut breach_list = Vec::new();
+    for prerequisite in &subject.prerequisite_list {
+      if !student.completed_list.contains(prerequisite) {
+        breach_list.push(RuleBreach::MissingPrerequisite(prerequisite.clone()));
+      }
+    }
+    for corequisite in &subject.corequisite_list {
+      if !takes(corequisite) {
+        breach_list.push(RuleBreach::MissingCorequisite(
+          corequisite.upgrade().unwrap().name.clone(),
+        ));
+      }
+    }
+    for exclusion in &subject.exclusion_list {
+      if takes(exclusion) {
+        breach_list.push(RuleBreach::Excluded(
+          exclusion.upgrade().unwrap().name.clone(),
+        ));
+      }
+    }
+    violation_list.extend(breach_list.into_iter().map(|breach| RuleViolation {
+      student_id: student.id.clone(),
+      subject: subject.name.clone(),
+      breach,
+    }));
+  }
+  violation_list
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Schedule, StudentBuilder};
+  use std::sync::Arc;
+
+  fn school() -> Schedule {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 1, 30, 10).unwrap();
+    high_school.new_department("Science", 1, 30, 10).unwrap();
+    high_school.new_department("Art", 1, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject_with_rules(
+        "Physics",
+        vec!["Science"],
+        SubjectRules {
+          prerequisite_list: vec!["Junior Science".to_string()],
+          corequisite_list: vec!["Calculus".to_string()],
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    high_school.new_subject("Art Design", vec!["Art"]).unwrap();
+    high_school
+      .new_subject_with_rules(
+        "Art Painting",
+        vec!["Art"],
+        SubjectRules {
+          exclusion_list: vec!["Art Design".to_string()],
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    high_school
+  }
+
+  #[test]
+  fn test_rules_allow_valid_choices() {
+    let mut high_school = school();
+    high_school
+      .new_student_with_completed(
+        "Person",
+        "One",
+        "1",
+        vec!["Physics", "Calculus", "Art Painting"],
+        vec!["Junior Science"],
+      )
+      .unwrap();
+    assert!(high_school.validate().is_empty());
+  }
+
+  #[test]
+  fn test_rule_violations() {
+    let high_school = school();
+    let student = StudentBuilder::new()
+      .first_name("Person")
+      .last_name("One")
+      .id("1")
+      .subject(Arc::downgrade(&high_school.get_subject("Physics").unwrap()))
+      .subject(Arc::downgrade(
+        &high_school.get_subject("Art Design").unwrap(),
+      ))
+      .subject(Arc::downgrade(
+        &high_school.get_subject("Art Painting").unwrap(),
+      ))
+      .build()
+      .unwrap();
+
+    let violation_list = rule_violations(&student);
+    assert_eq!(
+      violation_list
+        .iter()
+        .map(|x| x.breach.clone())
+        .collect::<Vec<_>>(),
+      vec![
+        RuleBreach::MissingPrerequisite("Junior Science".to_string()),
+        RuleBreach::MissingCorequisite("Calculus".to_string()),
+        RuleBreach::Excluded("Art Design".to_string()),
+      ]
+    );
+    assert_eq!(
+      violation_list[2].to_string(),
+      "Student (1) takes Art Painting alongside Art Design, which it excludes"
+    );
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_new_student_breaking_rules() {
+    let mut high_school = school();
+    high_school
+      .new_student("Person", "One", "1", vec!["Art Design", "Art Painting"])
+      .unwrap();
+  }
+
+  #[test]
+  fn test_new_student_lists_every_violation() {
+    let mut high_school = school();
+    let error = high_school
+      .new_student(
+        "Person",
+        "One",
+        "1",
+        vec!["Physics", "Art Design", "Art Painting"],
+      )
+      .unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "Generic Student (1) takes Physics without having completed Junior Science; \
+       Student (1) takes Physics without also taking Calculus; \
+       Student (1) takes Art Painting alongside Art Design, which it excludes"
+    );
+  }
+
+  #[test]
+  fn test_validate_students_added_directly() {
+    let mut high_school = school();
+    let student = StudentBuilder::new()
+      .first_name("Person")
+      .last_name("One")
+      .id("1")
+      .subject(Arc::downgrade(
+        &high_school.get_subject("Art Design").unwrap(),
+      ))
+      .subject(Arc::downgrade(
+        &high_school.get_subject("Art Painting").unwrap(),
+      ))
+      .build()
+      .unwrap();
+    high_school.student_list.push(Arc::new(student));
+    assert_eq!(high_school.validate().len(), 1);
+    assert!(high_school.sort().is_err());
+  }
+}
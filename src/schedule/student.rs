@@ -1,4 +1,4 @@
-use crate::{prelude::*, Subject};
+use crate::{prelude::*, Cohort, Subject};
 use std::sync::Weak;
 
 #[derive(Default)]
@@ -7,6 +7,10 @@ pub struct StudentBuilder {
   last_name: Option<String>,
   id: Option<String>,
   subject_list: Vec<Weak<Subject>>,
+  reserve_list: Vec<Weak<Subject>>,
+  compulsory_list: Vec<Weak<Subject>>,
+  completed_list: Vec<String>,
+  cohort: Option<Weak<Cohort>>,
 }
 
 #[derive(Debug)]
@@ -14,7 +18,52 @@ pub struct Student {
   pub first_name: String,
   pub last_name: String,
   pub id: String,
+  // Chosen subjects, from first choice to last
   pub subject_list: Vec<Weak<Subject>>,
+  // Subjects to take in place of choices that cannot be placed, in order of preference
+  pub reserve_list: Vec<Weak<Subject>>,
+  // Chosen subjects the student must be placed in, on top of subjects compulsory for everyone
+  pub compulsory_list: Vec<Weak<Subject>>,
+  // Subjects, by name, the student completed in earlier years
+  pub completed_list: Vec<String>,
+  // Year group the student belongs to, if the school has any
+  pub cohort: Option<Weak<Cohort>>,
+}
+
+impl Student {
+  // Number of slots the student's subjects take between them
+  pub fn slot_demand(&self) -> usize {
+    self
+      .subject_list
+      .iter()
+      .map(|x| x.upgrade().unwrap().slot_demand)
+      .sum()
+  }
+
+  pub fn is_compulsory(&self, subject: &Weak<Subject>) -> bool {
+    subject.upgrade().unwrap().compulsory || self.compulsory_list.iter().any(|x| x.ptr_eq(subject))
+  }
+
+  // Whether the student can be taught in the slot, which is any slot unless they are in a cohort
+  pub fn uses_slot(&self, slot: usize) -> bool {
+    self
+      .cohort
+      .as_ref()
+      .is_none_or(|cohort| cohort.upgrade().unwrap().uses_slot(slot))
+  }
+
+  // Number of slots the student can be taught in, out of the timetable's slot_count
+  pub fn slot_count(&self, slot_count: usize) -> usize {
+    match &self.cohort {
+      Some(cohort) => cohort.upgrade().unwrap().slot_list.len(),
+      None => slot_count,
+    }
+  }
+
+  // Position of the subject among the student's choices, 0 being their first choice
+  pub fn rank(&self, subject: &Weak<Subject>) -> Option<usize> {
+    self.subject_list.iter().position(|x| x.ptr_eq(subject))
+  }
 }
 
 impl StudentBuilder {
@@ -47,6 +96,36 @@ impl StudentBuilder {
       ..self
     }
   }
+  pub fn reserve(self, new_reserve: Weak<Subject>) -> Self {
+    let mut new_reserve_list = self.reserve_list;
+    new_reserve_list.push(new_reserve);
+    Self {
+      reserve_list: new_reserve_list,
+      ..self
+    }
+  }
+  pub fn compulsory(self, new_compulsory: Weak<Subject>) -> Self {
+    let mut new_compulsory_list = self.compulsory_list;
+    new_compulsory_list.push(new_compulsory);
+    Self {
+      compulsory_list: new_compulsory_list,
+      ..self
+    }
+  }
+  pub fn completed(self, new_completed: impl Into<String>) -> Self {
+    let mut new_completed_list = self.completed_list;
+    new_completed_list.push(new_completed.into());
+    Self {
+      completed_list: new_completed_list,
+      ..self
+    }
+  }
+  pub fn cohort(self, new_cohort: Weak<Cohort>) -> Self {
+    Self {
+      cohort: Some(new_cohort),
+      ..self
+    }
+  }
   // #[target_feature(enable = "let-else")]
   pub fn build(self) -> Result<Student> {
     let Some(first_name) = self.first_name else {
@@ -59,11 +138,33 @@ impl StudentBuilder {
       return Err(Error::Generic("Cannot build student without id".to_string()));
     };
     let subject_list = self.subject_list;
+    let reserve_list = self.reserve_list;
+    if reserve_list
+      .iter()
+      .any(|reserve| subject_list.iter().any(|x| x.ptr_eq(reserve)))
+    {
+      return Err(Error::Generic(
+        "Cannot build student with a reserve that is also a choice".to_string(),
+      ));
+    }
+    let compulsory_list = self.compulsory_list;
+    if !compulsory_list
+      .iter()
+      .all(|compulsory| subject_list.iter().any(|x| x.ptr_eq(compulsory)))
+    {
+      return Err(Error::Generic(
+        "Cannot build student with a compulsory subject that is not a choice".to_string(),
+      ));
+    }
     Ok(Student {
       first_name,
       last_name,
       id,
       subject_list,
+      reserve_list,
+      compulsory_list,
+      completed_list: self.completed_list,
+      cohort: self.cohort,
     })
   }
 }
@@ -169,4 +270,31 @@ mod tests {
     let student = student_builder.build().unwrap();
     assert_eq!(student.subject_list.len(), 2);
   }
+
+  #[test]
+  #[should_panic]
+  fn create_student_reserve_is_a_choice() {
+    let maths_department = Arc::new(Department {
+      name: "Maths Department".to_string(),
+      class_count: 10,
+      min_class_size: 10,
+      max_class_size: 15,
+    });
+
+    let calculus_subject = Arc::new(
+      SubjectBuilder::new()
+        .name("Calculus")
+        .department(Arc::downgrade(&maths_department))
+        .build()
+        .unwrap(),
+    );
+
+    let student_builder = StudentBuilder::new()
+      .first_name("Person")
+      .last_name("One")
+      .id("123")
+      .subject(Arc::downgrade(&calculus_subject))
+      .reserve(Arc::downgrade(&calculus_subject));
+    let _student = student_builder.build().unwrap();
+  }
 }
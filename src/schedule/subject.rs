@@ -5,12 +5,30 @@ use std::sync::Weak;
 pub struct SubjectBuilder {
   name: Option<String>,
   department_list: Vec<Weak<Department>>,
+  feature_list: Vec<String>,
+  slot_demand: Option<usize>,
+  compulsory: bool,
+  prerequisite_list: Vec<String>,
+  corequisite_list: Vec<Weak<Subject>>,
+  exclusion_list: Vec<Weak<Subject>>,
 }
 
 #[derive(Debug)]
 pub struct Subject {
   pub name: String,
   pub department_list: Vec<Weak<Department>>,
+  // Room features every class of the subject needs, such as "lab"
+  pub feature_list: Vec<String>,
+  // Number of slots the subject takes, each run as a class of the same students
+  pub slot_demand: usize,
+  // Every student who takes the subject must be placed in it
+  pub compulsory: bool,
+  // Subjects, by name, a student must have completed before taking this one
+  pub prerequisite_list: Vec<String>,
+  // Subjects a student must take alongside this one
+  pub corequisite_list: Vec<Weak<Subject>>,
+  // Subjects a student cannot take alongside this one
+  pub exclusion_list: Vec<Weak<Subject>>,
 }
 
 impl SubjectBuilder {
@@ -31,20 +49,76 @@ impl SubjectBuilder {
       ..self
     }
   }
+  pub fn feature(self, new_feature: impl Into<String>) -> Self {
+    let mut new_feature_list = self.feature_list;
+    new_feature_list.push(new_feature.into());
+    Self {
+      feature_list: new_feature_list,
+      ..self
+    }
+  }
+  pub fn slot_demand(self, new_slot_demand: usize) -> Self {
+    Self {
+      slot_demand: Some(new_slot_demand),
+      ..self
+    }
+  }
+  pub fn compulsory(self, new_compulsory: bool) -> Self {
+    Self {
+      compulsory: new_compulsory,
+      ..self
+    }
+  }
+  pub fn prerequisite(self, new_prerequisite: impl Into<String>) -> Self {
+    let mut new_prerequisite_list = self.prerequisite_list;
+    new_prerequisite_list.push(new_prerequisite.into());
+    Self {
+      prerequisite_list: new_prerequisite_list,
+      ..self
+    }
+  }
+  pub fn corequisite(self, new_corequisite: Weak<Subject>) -> Self {
+    let mut new_corequisite_list = self.corequisite_list;
+    new_corequisite_list.push(new_corequisite);
+    Self {
+      corequisite_list: new_corequisite_list,
+      ..self
+    }
+  }
+  pub fn exclusion(self, new_exclusion: Weak<Subject>) -> Self {
+    let mut new_exclusion_list = self.exclusion_list;
+    new_exclusion_list.push(new_exclusion);
+    Self {
+      exclusion_list: new_exclusion_list,
+      ..self
+    }
+  }
   // #[target_feature(enable = "let-else")]
   pub fn build(self) -> Result<Subject> {
     let Some(name) = self.name else {
       return Err(Error::Generic("Cannot build subject without name".to_string()));
     };
     let department_list = self.department_list;
-    if department_list.len() == 0 {
+    if department_list.is_empty() {
       return Err(Error::Generic(
         "Cannot build subject without any departments".to_string(),
       ));
     }
+    let slot_demand = self.slot_demand.unwrap_or(1);
+    if slot_demand == 0 {
+      return Err(Error::Generic(
+        "Cannot build subject that takes no slots".to_string(),
+      ));
+    }
     Ok(Subject {
       name,
       department_list,
+      feature_list: self.feature_list,
+      slot_demand,
+      compulsory: self.compulsory,
+      prerequisite_list: self.prerequisite_list,
+      corequisite_list: self.corequisite_list,
+      exclusion_list: self.exclusion_list,
     })
   }
 }
@@ -101,4 +175,22 @@ mod tests {
 
     assert_eq!(calculus_subject.department_list.len(), 1);
   }
+
+  #[test]
+  #[should_panic]
+  fn create_subject_no_slot_demand() {
+    let maths_department = Arc::new(Department {
+      name: "Maths Department".to_string(),
+      min_class_size: 20,
+      max_class_size: 30,
+      class_count: 20,
+    });
+
+    let _subject = SubjectBuilder::new()
+      .name("Calculus")
+      .department(Arc::downgrade(&maths_department))
+      .slot_demand(0)
+      .build()
+      .unwrap();
+  }
 }
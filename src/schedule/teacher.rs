@@ -0,0 +1,184 @@
+use crate::{prelude::*, timetable::Class, Department, Subject};
+use std::sync::Weak;
+
+#[derive(Default)]
+pub struct TeacherBuilder {
+  id: Option<String>,
+  name: Option<String>,
+  subject_list: Vec<Weak<Subject>>,
+  department_list: Vec<Weak<Department>>,
+  max_class_count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct Teacher {
+  pub id: String,
+  pub name: String,
+  // Subjects the teacher can teach, in any department
+  pub subject_list: Vec<Weak<Subject>>,
+  // Departments the teacher can teach any subject in
+  pub department_list: Vec<Weak<Department>>,
+  pub max_class_count: usize,
+}
+
+impl Teacher {
+  pub fn can_teach(&self, class: &Class) -> bool {
+    self.subject_list.iter().any(|x| x.ptr_eq(&class.subject))
+      || self
+        .department_list
+        .iter()
+        .any(|x| x.ptr_eq(&class.department))
+  }
+}
+
+impl TeacherBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn id(self, new_id: impl Into<String>) -> Self {
+    Self {
+      id: Some(new_id.into()),
+      ..self
+    }
+  }
+  pub fn name(self, new_name: impl Into<String>) -> Self {
+    Self {
+      name: Some(new_name.into()),
+      ..self
+    }
+  }
+  pub fn subject(self, new_subject: Weak<Subject>) -> Self {
+    let mut new_subject_list = self.subject_list;
+    new_subject_list.push(new_subject);
+    Self {
+      subject_list: new_subject_list,
+      ..self
+    }
+  }
+  pub fn department(self, new_department: Weak<Department>) -> Self {
+    let mut new_department_list = self.department_list;
+    new_department_list.push(new_department);
+    Self {
+      department_list: new_department_list,
+      ..self
+    }
+  }
+  pub fn max_class_count(self, new_max_class_count: usize) -> Self {
+    Self {
+      max_class_count: Some(new_max_class_count),
+      ..self
+    }
+  }
+  pub fn build(self) -> Result<Teacher> {
+    let Some(id) = self.id else {
+      return Err(Error::Generic(
+        "Cannot build teacher without id".to_string(),
+      ));
+    };
+    let Some(name) = self.name else {
+      return Err(Error::Generic(
+        "Cannot build teacher without name".to_string(),
+      ));
+    };
+    let Some(max_class_count) = self.max_class_count else {
+      return Err(Error::Generic(
+        "Cannot build teacher without a maximum class count".to_string(),
+      ));
+    };
+    if self.subject_list.is_empty() && self.department_list.is_empty() {
+      return Err(Error::Generic(
+        "Cannot build teacher without any subjects or departments".to_string(),
+      ));
+    }
+    Ok(Teacher {
+      id,
+      name,
+      subject_list: self.subject_list,
+      department_list: self.department_list,
+      max_class_count,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SubjectBuilder;
+  use std::sync::Arc;
+
+  #[test]
+  #[should_panic]
+  fn create_teacher_missing_qualifications() {
+    let _teacher = TeacherBuilder::new()
+      .id("T1")
+      .name("Teacher One")
+      .max_class_count(5)
+      .build()
+      .unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn create_teacher_missing_max_class_count() {
+    let maths_department = Arc::new(Department {
+      name: "Maths Department".to_string(),
+      min_class_size: 20,
+      max_class_size: 30,
+      class_count: 20,
+    });
+    let _teacher = TeacherBuilder::new()
+      .id("T1")
+      .name("Teacher One")
+      .department(Arc::downgrade(&maths_department))
+      .build()
+      .unwrap();
+  }
+
+  #[test]
+  fn teacher_can_teach() {
+    let maths_department = Arc::new(Department {
+      name: "Maths Department".to_string(),
+      min_class_size: 20,
+      max_class_size: 30,
+      class_count: 20,
+    });
+    let science_department = Arc::new(Department {
+      name: "Science Department".to_string(),
+      min_class_size: 20,
+      max_class_size: 30,
+      class_count: 20,
+    });
+    let calculus = Arc::new(
+      SubjectBuilder::new()
+        .name("Calculus")
+        .department(Arc::downgrade(&maths_department))
+        .build()
+        .unwrap(),
+    );
+    let physics = Arc::new(
+      SubjectBuilder::new()
+        .name("Physics")
+        .department(Arc::downgrade(&science_department))
+        .build()
+        .unwrap(),
+    );
+    let teacher = TeacherBuilder::new()
+      .id("T1")
+      .name("Teacher One")
+      .subject(Arc::downgrade(&calculus))
+      .max_class_count(5)
+      .build()
+      .unwrap();
+
+    assert!(teacher.can_teach(&Class::new(
+      Arc::downgrade(&calculus),
+      Arc::downgrade(&maths_department),
+      Vec::new()
+    )));
+    assert!(!teacher.can_teach(&Class::new(
+      Arc::downgrade(&physics),
+      Arc::downgrade(&science_department),
+      Vec::new()
+    )));
+  }
+}
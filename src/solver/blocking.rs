@@ -0,0 +1,1145 @@
+//! Designing option blocks, the subjects that run in each slot, before students are sorted
+
+use super::Solver;
+use crate::{
+  prelude::*, rules::combination_violations, timetable::Timetable, Class, Department, Schedule,
+  Student, Subject,
+};
+use rand::Rng;
+use std::{
+  fmt,
+  sync::{Arc, Weak},
+};
+
+#[derive(Debug, Clone)]
+pub struct BlockEntry {
+  pub subject: Weak<Subject>,
+  pub department: Weak<Department>,
+  // Parallel classes of the subject run in the block
+  pub class_count: usize,
+}
+
+// The subjects offered in each slot, with how many classes of each
+#[derive(Debug, Clone, Default)]
+pub struct BlockingPlan {
+  pub block_list: Vec<Vec<BlockEntry>>,
+}
+
+impl BlockingPlan {
+  pub fn new(block_count: usize) -> Self {
+    Self {
+      block_list: vec![Vec::new(); block_count],
+    }
+  }
+
+  pub fn add_class(&mut self, block: usize, subject: Weak<Subject>, department: Weak<Department>) {
+    match self.block_list[block]
+      .iter_mut()
+      .find(|x| x.subject.ptr_eq(&subject) && x.department.ptr_eq(&department))
+    {
+      Some(entry) => entry.class_count += 1,
+      None => self.block_list[block].push(BlockEntry {
+        subject,
+        department,
+        class_count: 1,
+      }),
+    }
+  }
+
+  pub fn offers(&self, block: usize, subject: &Weak<Subject>) -> bool {
+    self.block_list[block]
+      .iter()
+      .any(|x| x.subject.ptr_eq(subject))
+  }
+
+  // Whether the block runs a class of the entry's subject and department
+  fn runs(&self, block: usize, entry: &BlockEntry) -> bool {
+    self.class_count(block, entry) > 0
+  }
+
+  // Classes of the entry's subject and department the block runs
+  fn class_count(&self, block: usize, entry: &BlockEntry) -> usize {
+    self.block_list[block]
+      .iter()
+      .find(|x| x.subject.ptr_eq(&entry.subject) && x.department.ptr_eq(&entry.department))
+      .map_or(0, |x| x.class_count)
+  }
+
+  // Seats in the block's classes of the subject
+  pub fn seat_count(&self, block: usize, subject: &Weak<Subject>) -> usize {
+    self.block_list[block]
+      .iter()
+      .filter(|x| x.subject.ptr_eq(subject))
+      .map(|x| x.class_count * x.department.upgrade().unwrap().max_class_size)
+      .sum()
+  }
+
+  // Blocks the student could take each requested subject in, if classes had unlimited seats
+  pub fn fits(&self, student: &Student) -> bool {
+    let request_list = request_list(student);
+    let option_list: Vec<Vec<usize>> = request_list
+      .iter()
+      .map(|subject| {
+        (0..self.block_list.len())
+          .filter(|&block| student.uses_slot(block) && self.offers(block, subject))
+          .collect()
+      })
+      .collect();
+    match_requests(&option_list, self.block_list.len()).is_some()
+  }
+
+  // Students, in order, whose every request fits in a block with a seat left for them
+  pub fn fitted_student_count(&self, student_list: &[Arc<Student>]) -> usize {
+    let mut seat_list: Vec<Vec<(Weak<Subject>, usize)>> = (0..self.block_list.len())
+      .map(|block| {
+        self.block_list[block]
+          .iter()
+          .map(|x| (Weak::clone(&x.subject), self.seat_count(block, &x.subject)))
+          .collect()
+      })
+      .collect();
+    let mut fitted_count = 0;
+    for student in student_list {
+      let request_list = request_list(student);
+      let option_list: Vec<Vec<usize>> = request_list
+        .iter()
+        .map(|subject| {
+          (0..seat_list.len())
+            .filter(|&block| {
+              student.uses_slot(block)
+                && seat_list[block]
+                  .iter()
+                  .any(|(other, seats)| other.ptr_eq(subject) && *seats > 0)
+            })
+            .collect()
+        })
+        .collect();
+      let Some(block_list) = match_requests(&option_list, seat_list.len()) else {
+        continue;
+      };
+      for (subject, block) in request_list.iter().zip(block_list) {
+        let (_, seats) = seat_list[block]
+          .iter_mut()
+          .find(|(other, _)| other.ptr_eq(subject))
+          .unwrap();
+        *seats -= 1;
+      }
+      fitted_count += 1;
+    }
+    fitted_count
+  }
+}
+
+impl fmt::Display for BlockingPlan {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (block, entry_list) in self.block_list.iter().enumerate() {
+      if block > 0 {
+        writeln!(f)?;
+      }
+      write!(f, "Block {}:", block + 1)?;
+      for (index, entry) in entry_list.iter().enumerate() {
+        write!(
+          f,
+          "{} {} x{} ({})",
+          if index == 0 { "" } else { "," },
+          entry.subject.upgrade().unwrap().name,
+          entry.class_count,
+          entry.department.upgrade().unwrap().name
+        )?;
+      }
+    }
+    Ok(())
+  }
+}
+
+// Each of the student's subjects, repeated once for every slot it takes
+fn request_list(student: &Student) -> Vec<Weak<Subject>> {
+  student
+    .subject_list
+    .iter()
+    .flat_map(|subject| {
+      std::iter::repeat_n(Weak::clone(subject), subject.upgrade().unwrap().slot_demand)
+    })
+    .collect()
+}
+
+// Gives every request a different block out of its options, or None if that cannot be done
+fn match_requests(option_list: &[Vec<usize>], block_count: usize) -> Option<Vec<usize>> {
+  match_most_requests(option_list, block_count)
+    .into_iter()
+    .collect()
+}
+
+// Gives as many requests as possible a different block out of their options
+fn match_most_requests(option_list: &[Vec<usize>], block_count: usize) -> Vec<Option<usize>> {
+  fn augment(
+    request: usize,
+    option_list: &[Vec<usize>],
+    block_request: &mut [Option<usize>],
+    visited: &mut [bool],
+  ) -> bool {
+    for &block in &option_list[request] {
+      if visited[block] {
+        continue;
+      }
+      visited[block] = true;
+      if block_request[block]
+        .is_none_or(|other| augment(other, option_list, block_request, visited))
+      {
+        block_request[block] = Some(request);
+        return true;
+      }
+    }
+    false
+  }
+
+  let mut block_request = vec![None; block_count];
+  for request in 0..option_list.len() {
+    augment(
+      request,
+      option_list,
+      &mut block_request,
+      &mut vec![false; block_count],
+    );
+  }
+  let mut request_block = vec![None; option_list.len()];
+  for (block, request) in block_request.into_iter().enumerate() {
+    if let Some(request) = request {
+      request_block[request] = Some(block);
+    }
+  }
+  request_block
+}
+
+// Whether the student has, or could take, a seat in an ungrouped class of the subject in the slot
+fn has_seat(
+  timetable: &Timetable,
+  slot: usize,
+  subject: &Weak<Subject>,
+  student: &Weak<Student>,
+) -> bool {
+  let slot = timetable.slot_list[slot].lock().unwrap();
+  slot.is_open_to(student)
+    && slot
+      .class_list
+      .iter()
+      .enumerate()
+      .filter(|(_, class)| class.subject.ptr_eq(subject) && class.group.is_none())
+      // Classes nobody joined are removed, but are still on offer
+      .filter(|(_, class)| !class.removed || class.student_list.is_empty())
+      .any(|(index, class)| class.contains(Weak::clone(student)) || slot.can_grow(index))
+}
+
+// Groups of the multi-slot subject, with their slots, that the student has, or could take, a
+// seat in across every one of those slots
+fn seated_group_list(
+  timetable: &Timetable,
+  subject: &Weak<Subject>,
+  student: &Weak<Student>,
+) -> Vec<(usize, Vec<usize>)> {
+  let mut group_list: Vec<(usize, Vec<usize>, bool)> = Vec::new();
+  for (slot_index, slot) in timetable.slot_list.iter().enumerate() {
+    let slot = slot.lock().unwrap();
+    for (class_index, class) in slot.class_list.iter().enumerate() {
+      let Some(group) = class.group else {
+        continue;
+      };
+      // Groups nobody joined are removed, but are still on offer
+      if !class.subject.ptr_eq(subject) || (class.removed && !class.student_list.is_empty()) {
+        continue;
+      }
+      let has_seat = slot.is_open_to(student)
+        && (class.contains(Weak::clone(student)) || slot.can_grow(class_index));
+      match group_list.iter_mut().find(|(other, _, _)| *other == group) {
+        Some((_, slot_list, seated)) => {
+          slot_list.push(slot_index);
+          *seated &= has_seat;
+        }
+        None => group_list.push((group, vec![slot_index], has_seat)),
+      }
+    }
+  }
+  group_list
+    .into_iter()
+    .filter(|(_, _, seated)| *seated)
+    .map(|(group, slot_list, _)| (group, slot_list))
+    .collect()
+}
+
+// Where a student's subjects fit: a whole group for each multi-slot subject that gets one, and
+// a slot for each other subject that gets one
+struct Fit {
+  group_list: Vec<(Weak<Subject>, usize)>,
+  slot_list: Vec<(Weak<Subject>, usize)>,
+  // Slots given to the subjects
+  filled: usize,
+}
+
+// Seats for as many of the subjects' slots as possible
+fn fit_subjects(
+  timetable: &Timetable,
+  student: &Weak<Student>,
+  subject_list: &[Weak<Subject>],
+) -> Fit {
+  let (multi_list, single_list): (Vec<Weak<Subject>>, Vec<Weak<Subject>>) = subject_list
+    .iter()
+    .cloned()
+    .partition(|subject| subject.upgrade().unwrap().slot_demand > 1);
+  let group_option_list: Vec<Vec<(usize, Vec<usize>)>> = multi_list
+    .iter()
+    .map(|subject| seated_group_list(timetable, subject, student))
+    .collect();
+  let option_list: Vec<Vec<usize>> = single_list
+    .iter()
+    .map(|subject| {
+      (0..timetable.slot_list.len())
+        .filter(|&slot| has_seat(timetable, slot, subject, student))
+        .collect()
+    })
+    .collect();
+
+  // Tries every choice of group, or none, for each multi-slot subject in turn, and matches the
+  // other subjects into the slots left over
+  struct Search<'a> {
+    group_option_list: &'a [Vec<(usize, Vec<usize>)>],
+    option_list: &'a [Vec<usize>],
+    slot_count: usize,
+    // Group chosen for each multi-slot subject and slot matched to each other subject
+    best_chosen: Vec<Option<usize>>,
+    best_slot_list: Vec<Option<usize>>,
+    best_filled: Option<usize>,
+  }
+  impl Search<'_> {
+    fn search(&mut self, chosen: &mut Vec<Option<usize>>, used: &mut [bool], filled: usize) {
+      if chosen.len() == self.group_option_list.len() {
+        let option_list: Vec<Vec<usize>> = self
+          .option_list
+          .iter()
+          .map(|x| x.iter().copied().filter(|&slot| !used[slot]).collect())
+          .collect();
+        let slot_list = match_most_requests(&option_list, self.slot_count);
+        let filled = filled + slot_list.iter().flatten().count();
+        if self.best_filled.is_none_or(|best| filled > best) {
+          self.best_chosen = chosen.clone();
+          self.best_slot_list = slot_list;
+          self.best_filled = Some(filled);
+        }
+        return;
+      }
+      let group_option_list = self.group_option_list;
+      for (option, (_, slot_list)) in group_option_list[chosen.len()].iter().enumerate() {
+        if slot_list.iter().any(|&slot| used[slot]) {
+          continue;
+        }
+        for &slot in slot_list {
+          used[slot] = true;
+        }
+        chosen.push(Some(option));
+        self.search(chosen, used, filled + slot_list.len());
+        chosen.pop();
+        for &slot in slot_list {
+          used[slot] = false;
+        }
+      }
+      chosen.push(None);
+      self.search(chosen, used, filled);
+      chosen.pop();
+    }
+  }
+
+  let mut search = Search {
+    group_option_list: &group_option_list,
+    option_list: &option_list,
+    slot_count: timetable.slot_list.len(),
+    best_chosen: Vec::new(),
+    best_slot_list: Vec::new(),
+    best_filled: None,
+  };
+  search.search(
+    &mut Vec::new(),
+    &mut vec![false; timetable.slot_list.len()],
+    0,
+  );
+  let group_list = multi_list
+    .into_iter()
+    .zip(search.best_chosen)
+    .enumerate()
+    .filter_map(|(index, (subject, option))| {
+      option.map(|option| (subject, group_option_list[index][option].0))
+    })
+    .collect();
+  let slot_list = single_list
+    .into_iter()
+    .zip(search.best_slot_list)
+    .filter_map(|(subject, slot)| slot.map(|slot| (subject, slot)))
+    .collect();
+  Fit {
+    group_list,
+    slot_list,
+    filled: search.best_filled.unwrap(),
+  }
+}
+
+// The other subject combinations closest to what the student asked for, swapping as few
+// subjects as possible, that fit into the classes of the timetable with seats left. Compulsory
+// subjects are never swapped out, and combinations must keep to the subject rules and the
+// student's cohort.
+pub fn alternative_combinations(
+  timetable: &Timetable,
+  student: &Arc<Student>,
+  subject_list: &[Arc<Subject>],
+  limit: usize,
+) -> Vec<Vec<Weak<Subject>>> {
+  let weak_student = Arc::downgrade(student);
+  let fits = |combination: &[Weak<Subject>]| {
+    let slot_count: usize = combination
+      .iter()
+      .map(|subject| subject.upgrade().unwrap().slot_demand)
+      .sum();
+    fit_subjects(timetable, &weak_student, combination).filled == slot_count
+  };
+
+  let requested = &student.subject_list;
+  let replacement_list: Vec<Weak<Subject>> = subject_list
+    .iter()
+    .map(Arc::downgrade)
+    .filter(|subject| !requested.iter().any(|x| x.ptr_eq(subject)))
+    .filter(|subject| {
+      student
+        .cohort
+        .as_ref()
+        .is_none_or(|cohort| cohort.upgrade().unwrap().offers(subject))
+    })
+    .collect();
+  let mut alternative_list = Vec::new();
+  for swap_count in 1..=requested.len().min(2) {
+    let removal_list: Vec<Vec<usize>> = if swap_count == 1 {
+      (0..requested.len()).map(|x| vec![x]).collect()
+    } else {
+      (0..requested.len())
+        .flat_map(|one| (one + 1..requested.len()).map(move |two| vec![one, two]))
+        .collect()
+    };
+    let addition_list: Vec<Vec<usize>> = if swap_count == 1 {
+      (0..replacement_list.len()).map(|x| vec![x]).collect()
+    } else {
+      (0..replacement_list.len())
+        .flat_map(|one| (one + 1..replacement_list.len()).map(move |two| vec![one, two]))
+        .collect()
+    };
+    for removal in &removal_list {
+      if removal
+        .iter()
+        .any(|&index| student.is_compulsory(&requested[index]))
+      {
+        continue;
+      }
+      for addition in &addition_list {
+        let mut combination: Vec<Weak<Subject>> = requested
+          .iter()
+          .enumerate()
+          .filter(|(index, _)| !removal.contains(index))
+          .map(|(_, subject)| Weak::clone(subject))
+          .collect();
+        combination.extend(addition.iter().map(|&x| Weak::clone(&replacement_list[x])));
+        if combination_violations(student, &combination).is_empty() && fits(&combination) {
+          alternative_list.push(combination);
+          if alternative_list.len() >= limit {
+            return alternative_list;
+          }
+        }
+      }
+    }
+    if !alternative_list.is_empty() {
+      break;
+    }
+  }
+  alternative_list
+}
+
+// Sorts students into the classes of the schedule's blocking plan, without opening any others.
+// Students are seated in as many of their subjects as the plan allows, taking a multi-slot
+// subject in a whole group of its classes or not at all, and classes too small to run are
+// removed.
+#[derive(Debug, Default, Clone)]
+pub struct BlockSolver {}
+
+impl BlockSolver {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl Solver for BlockSolver {
+  fn name(&self) -> String {
+    "Fixed blocks".to_string()
+  }
+  fn solve(&mut self, schedule: &Schedule) -> Result<Timetable> {
+    let Some(plan) = &schedule.blocking_plan else {
+      return Err(Error::Generic(
+        "Cannot sort into blocks without a blocking plan".to_string(),
+      ));
+    };
+    let mut timetable = schedule.empty_timetable();
+    if plan.block_list.len() != timetable.slot_list.len() {
+      return Err(Error::Generic(format!(
+        "Blocking plan has {} blocks but there are {} slots",
+        plan.block_list.len(),
+        timetable.slot_list.len()
+      )));
+    }
+    // Classes of a multi-slot subject run as groups, one class in each of as many blocks as the
+    // subject takes, and any left over that cannot make up a whole group are not run
+    let mut group = 0;
+    for (block, entry_list) in plan.block_list.iter().enumerate() {
+      for entry in entry_list {
+        let slot_demand = entry.subject.upgrade().unwrap().slot_demand;
+        if slot_demand < 2 || (0..block).any(|other| plan.runs(other, entry)) {
+          continue;
+        }
+        let mut count_list: Vec<usize> = (0..plan.block_list.len())
+          .map(|other| plan.class_count(other, entry))
+          .collect();
+        loop {
+          let mut block_list: Vec<usize> = (0..count_list.len())
+            .filter(|&other| count_list[other] > 0)
+            .collect();
+          if block_list.len() < slot_demand {
+            break;
+          }
+          block_list.sort_by_key(|&other| std::cmp::Reverse(count_list[other]));
+          for &other in &block_list[..slot_demand] {
+            count_list[other] -= 1;
+            timetable.slot_list[other]
+              .lock()
+              .unwrap()
+              .class_list
+              .push(Class {
+                group: Some(group),
+                ..Class::new(
+                  Weak::clone(&entry.subject),
+                  Weak::clone(&entry.department),
+                  Vec::new(),
+                )
+              });
+          }
+          group += 1;
+        }
+      }
+    }
+    for (block, entry_list) in plan.block_list.iter().enumerate() {
+      let mut slot = timetable.slot_list[block].lock().unwrap();
+      for entry in entry_list {
+        if entry.subject.upgrade().unwrap().slot_demand > 1 {
+          continue;
+        }
+        for _ in 0..entry.class_count {
+          slot.class_list.push(Class::new(
+            Weak::clone(&entry.subject),
+            Weak::clone(&entry.department),
+            Vec::new(),
+          ));
+        }
+      }
+    }
+
+    // Students take a multi-slot subject in a whole group or not at all
+    for student in &schedule.student_list {
+      let weak_student = Arc::downgrade(student);
+      let fit = fit_subjects(&timetable, &weak_student, &student.subject_list);
+      for (_, group) in fit.group_list {
+        timetable.join_group(Weak::clone(&weak_student), group)?;
+      }
+      for (subject, block) in fit.slot_list {
+        timetable.slot_list[block]
+          .lock()
+          .unwrap()
+          .add_student_to_subject(Weak::clone(&weak_student), subject)?;
+      }
+    }
+
+    // Reserves can still fill classes that nobody chose. Students of classes that stay below
+    // their department's minimum move to other classes of the plan if they can.
+    timetable.place_reserves(&schedule.student_list, false);
+    let displaced_list = timetable.remove_small_classes();
+    timetable.place_displaced_students(displaced_list);
+    for slot in &timetable.slot_list {
+      for class in &mut slot.lock().unwrap().class_list {
+        if class.student_list.is_empty() {
+          class.removed = true;
+        }
+      }
+    }
+    Ok(timetable)
+  }
+}
+
+// Proposes a blocking plan from the subjects students have asked for, with enough classes of
+// each subject for everyone who chose it, laid out so that as many students as possible can
+// take their whole combination
+#[derive(Debug, Clone)]
+pub struct BlockDesigner {
+  // Attempts at moving or swapping classes between blocks after the first layout
+  pub iteration_limit: usize,
+}
+
+impl Default for BlockDesigner {
+  fn default() -> Self {
+    Self {
+      iteration_limit: 2000,
+    }
+  }
+}
+
+impl BlockDesigner {
+  pub fn new(iteration_limit: usize) -> Self {
+    Self { iteration_limit }
+  }
+
+  pub fn design(&self, schedule: &Schedule) -> Result<BlockingPlan> {
+    let block_count = schedule.slot_count();
+    if block_count == 0 {
+      return Err(Error::Generic(
+        "Cannot design blocks for a school with no slots".to_string(),
+      ));
+    }
+    let subject_count = schedule.subject_list.len();
+    let subject_index = |subject: &Weak<Subject>| {
+      schedule
+        .subject_list
+        .iter()
+        .position(|x| Arc::as_ptr(x) == subject.as_ptr())
+        .unwrap()
+    };
+
+    let mut demand: Vec<usize> = vec![0; subject_count];
+    let mut together: Vec<Vec<usize>> = vec![vec![0; subject_count]; subject_count];
+    // Blocks each subject can run in: those of the cohorts offering it, or any block once a
+    // student outside every cohort chose it
+    let mut open_block_list: Vec<Vec<bool>> = schedule
+      .subject_list
+      .iter()
+      .map(|subject| {
+        let subject = Arc::downgrade(subject);
+        (0..block_count)
+          .map(|block| {
+            schedule
+              .cohort_list
+              .iter()
+              .any(|cohort| cohort.offers(&subject) && cohort.uses_slot(block))
+          })
+          .collect()
+      })
+      .collect();
+    for student in &schedule.student_list {
+      let index_list: Vec<usize> = student.subject_list.iter().map(subject_index).collect();
+      for &one in &index_list {
+        if student.cohort.is_none() {
+          open_block_list[one] = vec![true; block_count];
+        }
+        demand[one] += 1;
+        for &two in &index_list {
+          if one != two {
+            together[one][two] += 1;
+          }
+        }
+      }
+    }
+
+    // Enough classes of each subject for everyone who chose it, in the first department it can
+    // run in with that many students, and once more for each extra slot the subject takes
+    let mut unit_list: Vec<(usize, usize)> = Vec::new();
+    let mut class_count = vec![0; subject_count];
+    for (subject, subject_arc) in schedule.subject_list.iter().enumerate() {
+      let Some(department) = subject_arc.department_list.iter().find_map(|department| {
+        let department = department.upgrade().unwrap();
+        (department.min_class_size <= demand[subject]).then_some(department)
+      }) else {
+        continue;
+      };
+      let department = schedule
+        .department_list
+        .iter()
+        .position(|x| Arc::ptr_eq(x, &department))
+        .unwrap();
+      let max_class_size = schedule.department_list[department].max_class_size.max(1);
+      class_count[subject] = demand[subject].div_ceil(max_class_size);
+      for _ in 0..class_count[subject] * subject_arc.slot_demand {
+        unit_list.push((subject, department));
+      }
+    }
+    // Subjects with few classes are the hardest to place, so go first
+    unit_list
+      .sort_by_key(|&(subject, _)| (class_count[subject], std::cmp::Reverse(demand[subject])));
+
+    let department_count = schedule.department_list.len();
+    let mut room_use = vec![vec![0; department_count]; block_count];
+    // Whether a block could hold its classes with one more of the department, and one fewer of
+    // the other when two classes swap
+    let has_room = |use_list: &[usize], department: usize, other: Option<usize>| {
+      let mut use_list = use_list.to_vec();
+      use_list[department] += 1;
+      if let Some(other) = other {
+        use_list[other] -= 1;
+      }
+      schedule.rooms_fit(&use_list)
+    };
+    let mut unit_block: Vec<Option<usize>> = vec![None; unit_list.len()];
+    for unit in 0..unit_list.len() {
+      let (subject, department) = unit_list[unit];
+      // Avoid blocks that already run subjects chosen alongside this one, or this one itself
+      let cost = |block: usize| -> usize {
+        (0..unit)
+          .filter(|&other| unit_block[other] == Some(block))
+          .map(|other| {
+            let other_subject = unit_list[other].0;
+            if other_subject == subject {
+              demand[subject]
+            } else {
+              together[subject][other_subject].div_ceil(class_count[other_subject])
+            }
+          })
+          .sum()
+      };
+      unit_block[unit] = (0..block_count)
+        .filter(|&block| {
+          open_block_list[subject][block] && has_room(&room_use[block], department, None)
+        })
+        .min_by_key(|&block| (cost(block), block));
+      if let Some(block) = unit_block[unit] {
+        room_use[block][department] += 1;
+      }
+    }
+
+    let build = |unit_block: &[Option<usize>]| {
+      let mut plan = BlockingPlan::new(block_count);
+      for (&(subject, department), block) in unit_list.iter().zip(unit_block) {
+        if let Some(block) = *block {
+          plan.add_class(
+            block,
+            Arc::downgrade(&schedule.subject_list[subject]),
+            Arc::downgrade(&schedule.department_list[department]),
+          );
+        }
+      }
+      plan
+    };
+
+    // Move a class to another block, or swap two classes, whenever no fewer students fit
+    let mut rng = schedule.rng();
+    let mut fitted_count = build(&unit_block).fitted_student_count(&schedule.student_list);
+    let placed_unit_list: Vec<usize> = (0..unit_list.len())
+      .filter(|&unit| unit_block[unit].is_some())
+      .collect();
+    for _ in 0..self.iteration_limit {
+      if placed_unit_list.is_empty() || block_count < 2 {
+        break;
+      }
+      let unit = placed_unit_list[rng.gen_range(0..placed_unit_list.len())];
+      let from = unit_block[unit].unwrap();
+      let department = unit_list[unit].1;
+      let previous = unit_block.clone();
+      if rng.gen_bool(0.5) {
+        let to = rng.gen_range(0..block_count);
+        if to == from
+          || !open_block_list[unit_list[unit].0][to]
+          || !has_room(&room_use[to], department, None)
+        {
+          continue;
+        }
+        unit_block[unit] = Some(to);
+      } else {
+        let other = placed_unit_list[rng.gen_range(0..placed_unit_list.len())];
+        let to = unit_block[other].unwrap();
+        let other_department = unit_list[other].1;
+        // Classes of different departments each need a free room in the block they move to
+        if to == from
+          || !open_block_list[unit_list[unit].0][to]
+          || !open_block_list[unit_list[other].0][from]
+          || (department != other_department
+            && (!has_room(&room_use[to], department, Some(other_department))
+              || !has_room(&room_use[from], other_department, Some(department))))
+        {
+          continue;
+        }
+        unit_block[unit] = Some(to);
+        unit_block[other] = Some(from);
+      }
+
+      let candidate_count = build(&unit_block).fitted_student_count(&schedule.student_list);
+      if candidate_count >= fitted_count {
+        fitted_count = candidate_count;
+        room_use = vec![vec![0; department_count]; block_count];
+        for (&(_, department), block) in unit_list.iter().zip(&unit_block) {
+          if let Some(block) = *block {
+            room_use[block][department] += 1;
+          }
+        }
+      } else {
+        unit_block = previous;
+      }
+    }
+
+    Ok(build(&unit_block))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_design_blocks() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 5, 30, 5).unwrap();
+    high_school.new_department("Science", 5, 30, 5).unwrap();
+    high_school.new_department("Art", 5, 30, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    for index in 0..20 {
+      let id = index.to_string();
+      let subjects = if index < 10 {
+        vec!["Calculus", "Physics"]
+      } else {
+        vec!["Calculus", "Painting"]
+      };
+      high_school
+        .new_student("Person", &id, &id, subjects)
+        .unwrap();
+    }
+    high_school.seed = Some(1);
+
+    let plan = BlockDesigner::default().design(&high_school).unwrap();
+
+    // Calculus has to run on its own, with physics and painting sharing the other block
+    assert_eq!(plan.fitted_student_count(&high_school.student_list), 20);
+    assert!(high_school.student_list.iter().all(|x| plan.fits(x)));
+    let calculus = Arc::downgrade(&high_school.get_subject("Calculus").unwrap());
+    let block = (0..2).find(|&x| plan.offers(x, &calculus)).unwrap();
+    assert_eq!(plan.block_list[block].len(), 1);
+  }
+
+  #[test]
+  fn test_design_blocks_swap_keeps_rooms() {
+    for seed in 0..10 {
+      let mut high_school = Schedule::with_slot_count(2);
+      high_school.seed = Some(seed);
+      high_school.new_department("Maths", 1, 1, 1).unwrap();
+      high_school.new_department("Science", 1, 30, 1).unwrap();
+      high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+      high_school.new_subject("Physics", vec!["Science"]).unwrap();
+      for id in ["1", "2"] {
+        high_school
+          .new_student("Person", id, id, vec!["Calculus"])
+          .unwrap();
+      }
+      high_school
+        .new_student("Person", "3", "3", vec!["Physics"])
+        .unwrap();
+
+      // Maths only has room for one class a block, so swapping with Physics cannot double up
+      let plan = BlockDesigner::default().design(&high_school).unwrap();
+      for block in &plan.block_list {
+        let maths_count: usize = block
+          .iter()
+          .filter(|x| x.department.upgrade().unwrap().name == "Maths")
+          .map(|x| x.class_count)
+          .sum();
+        assert!(maths_count <= 1);
+      }
+    }
+  }
+
+  #[test]
+  fn test_design_blocks_cohorts() {
+    for seed in 0..5 {
+      let mut high_school = Schedule::with_slot_count(4);
+      high_school.seed = Some(seed);
+      high_school.new_department("Maths", 1, 30, 2).unwrap();
+      high_school.new_subject("Algebra", vec!["Maths"]).unwrap();
+      high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+      high_school
+        .new_cohort("Year 9", vec!["Algebra"], vec![0, 1])
+        .unwrap();
+      high_school
+        .new_cohort("Year 12", vec!["Calculus"], vec![2, 3])
+        .unwrap();
+      high_school
+        .new_student_in_cohort("Person", "1", "1", "Year 9", vec!["Algebra"])
+        .unwrap();
+      high_school
+        .new_student_in_cohort("Person", "2", "2", "Year 12", vec!["Calculus"])
+        .unwrap();
+
+      // Each subject only runs in the blocks of the cohort that takes it
+      let plan = BlockDesigner::default().design(&high_school).unwrap();
+      assert_eq!(plan.fitted_student_count(&high_school.student_list), 2);
+      let algebra = Arc::downgrade(&high_school.get_subject("Algebra").unwrap());
+      let calculus = Arc::downgrade(&high_school.get_subject("Calculus").unwrap());
+      assert!(!plan.offers(2, &algebra) && !plan.offers(3, &algebra));
+      assert!(!plan.offers(0, &calculus) && !plan.offers(1, &calculus));
+    }
+  }
+
+  #[test]
+  fn test_plan_seat_count() {
+    let mut high_school = Schedule::with_slot_count(1);
+    high_school.new_department("Maths", 1, 2, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    for id in ["1", "2", "3"] {
+      high_school
+        .new_student("Person", id, id, vec!["Calculus"])
+        .unwrap();
+    }
+    let calculus = Arc::downgrade(&high_school.get_subject("Calculus").unwrap());
+    let maths = Arc::downgrade(&high_school.get_department("Maths").unwrap());
+    let mut plan = BlockingPlan::new(1);
+    plan.add_class(0, Weak::clone(&calculus), Weak::clone(&maths));
+
+    // One class of two only seats two of the three students
+    assert_eq!(plan.seat_count(0, &calculus), 2);
+    assert_eq!(plan.fitted_student_count(&high_school.student_list), 2);
+    plan.add_class(0, calculus, maths);
+    assert_eq!(plan.fitted_student_count(&high_school.student_list), 3);
+    assert_eq!(plan.to_string(), "Block 1: Calculus x2 (Maths)");
+  }
+
+  #[test]
+  fn test_sort_into_blocks() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 1, 2, 5).unwrap();
+    high_school.new_department("Science", 1, 30, 5).unwrap();
+    high_school.new_department("Art", 1, 30, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .add_blocked_classes(0, "Calculus", "Maths", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Physics", "Science", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Painting", "Art", 1)
+      .unwrap();
+    for id in ["1", "2"] {
+      high_school
+        .new_student("Person", id, id, vec!["Calculus", "Physics"])
+        .unwrap();
+    }
+    // Calculus is full by the time this student is sorted, and painting clashes with physics
+    high_school
+      .new_student("Person", "3", "3", vec!["Physics", "Painting"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    // Only the declared classes are made, and painting is removed as nobody could join it
+    assert_eq!(report.classes_created, 3);
+    assert_eq!(report.classes_removed, 1);
+    assert_eq!(report.fully_placed_student_count(), 2);
+    let student_report = report.get_student_report("3").unwrap();
+    assert_eq!(student_report.placed.len(), 1);
+    assert_eq!(student_report.unplaced.len(), 1);
+
+    // Calculus is the only other subject, and it has no seats left
+    assert!(student_report.alternative_list.is_empty());
+    assert!(report
+      .to_string()
+      .contains("Students with every subject: 2 of 3"));
+  }
+
+  #[test]
+  fn test_sort_multi_slot_subject_into_blocks() {
+    let mut high_school = Schedule::with_slot_count(3);
+    high_school.new_department("Maths", 1, 30, 5).unwrap();
+    high_school.new_department("Science", 1, 30, 5).unwrap();
+    high_school.new_department("Art", 1, 30, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject_with_slot_demand("Physics", vec!["Science"], 2)
+      .unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .add_blocked_classes(0, "Physics", "Science", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Physics", "Science", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Calculus", "Maths", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(2, "Painting", "Art", 1)
+      .unwrap();
+    high_school
+      .new_student("Person", "1", "1", vec!["Physics", "Painting"])
+      .unwrap();
+    // Calculus clashes with the second physics class, so this student can only take one
+    high_school
+      .new_student("Person", "2", "2", vec!["Calculus", "Physics"])
+      .unwrap();
+    high_school
+      .new_student("Person", "3", "3", vec!["Calculus", "Painting"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    let physics = Arc::downgrade(&high_school.get_subject("Physics").unwrap());
+    let student_report = report.get_student_report("2").unwrap();
+    assert_eq!(student_report.placed.len(), 1);
+    assert!(student_report.placed[0].ptr_eq(&physics));
+    // Both physics classes are one group, and each student is in both of them
+    let physics_class_list: Vec<Class> = high_school
+      .timetable
+      .slot_list
+      .iter()
+      .flat_map(|slot| slot.lock().unwrap().class_list.clone())
+      .filter(|class| !class.removed && class.subject.ptr_eq(&physics))
+      .collect();
+    assert_eq!(physics_class_list.len(), 2);
+    assert!(physics_class_list[0].group.is_some());
+    assert_eq!(physics_class_list[0].group, physics_class_list[1].group);
+    assert_eq!(physics_class_list[0].student_list.len(), 2);
+    assert_eq!(physics_class_list[1].student_list.len(), 2);
+  }
+
+  #[test]
+  fn test_sort_into_blocks_below_minimum() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 2, 30, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject("Statistics", vec!["Maths"])
+      .unwrap();
+    high_school
+      .add_blocked_classes(0, "Calculus", "Maths", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Statistics", "Maths", 1)
+      .unwrap();
+    high_school
+      .new_student("Person", "1", "1", vec!["Calculus", "Statistics"])
+      .unwrap();
+    high_school
+      .new_student("Person", "2", "2", vec!["Calculus"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    // Statistics only has one student against a minimum of two, so cannot run
+    assert_eq!(report.classes_removed, 1);
+    let student_report = report.get_student_report("1").unwrap();
+    assert_eq!(student_report.placed.len(), 1);
+    let statistics = Arc::downgrade(&high_school.get_subject("Statistics").unwrap());
+    assert!(student_report.dropped[0].ptr_eq(&statistics));
+  }
+
+  #[test]
+  fn test_alternative_combinations() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 1, 30, 5).unwrap();
+    high_school.new_department("Science", 1, 30, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject("Statistics", vec!["Maths"])
+      .unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school
+      .add_blocked_classes(0, "Calculus", "Maths", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(0, "Statistics", "Maths", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Physics", "Science", 1)
+      .unwrap();
+    high_school
+      .new_student("Person", "1", "1", vec!["Calculus", "Statistics"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    // Calculus and statistics share a block, so one of them has to become physics
+    let student_report = report.get_student_report("1").unwrap();
+    assert!(!student_report.is_fully_placed());
+    let physics = Arc::downgrade(&high_school.get_subject("Physics").unwrap());
+    assert_eq!(student_report.alternative_list.len(), 2);
+    assert!(student_report
+      .alternative_list
+      .iter()
+      .all(|combination| combination.len() == 2 && combination.iter().any(|x| x.ptr_eq(&physics))));
+    assert!(report
+      .to_string()
+      .contains("Students offered other combinations: 1"));
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_blocking_plan_wrong_block_count() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.set_blocking_plan(BlockingPlan::new(3)).unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_blocked_class_wrong_department() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 1, 30, 5).unwrap();
+    high_school.new_department("Art", 1, 30, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .add_blocked_classes(0, "Calculus", "Art", 1)
+      .unwrap();
+  }
+
+  #[test]
+  fn test_alternative_combinations_keep_rules() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 1, 30, 5).unwrap();
+    high_school.new_department("Science", 1, 30, 5).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject("Statistics", vec!["Maths"])
+      .unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school
+      .new_subject_with_rules(
+        "Chemistry",
+        vec!["Science"],
+        crate::SubjectRules {
+          exclusion_list: vec!["Calculus".to_string()],
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    high_school
+      .add_blocked_classes(0, "Calculus", "Maths", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(0, "Statistics", "Maths", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Physics", "Science", 1)
+      .unwrap();
+    high_school
+      .add_blocked_classes(1, "Chemistry", "Science", 1)
+      .unwrap();
+    high_school
+      .new_student_with_compulsory(
+        "Person",
+        "1",
+        "1",
+        vec!["Calculus", "Statistics"],
+        vec!["Calculus"],
+      )
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    // Calculus has to stay, and chemistry cannot be taken alongside it
+    let student_report = report.get_student_report("1").unwrap();
+    let calculus = Arc::downgrade(&high_school.get_subject("Calculus").unwrap());
+    let physics = Arc::downgrade(&high_school.get_subject("Physics").unwrap());
+    assert_eq!(student_report.alternative_list.len(), 1);
+    let combination = &student_report.alternative_list[0];
+    assert!(combination[0].ptr_eq(&calculus));
+    assert!(combination[1].ptr_eq(&physics));
+  }
+}
@@ -0,0 +1,806 @@
+use super::{GreedySolver, Solver};
+use crate::{prelude::*, room::rooms_fit, timetable::Timetable, Class, Schedule};
+use std::{
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+// Allowed rounding in sums of request weights when comparing timetables
+const VALUE_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExactStatus {
+  // The search finished, so no timetable leaves a lower unmet request penalty
+  Optimal,
+  // The time limit was reached, or the school has rooms or compulsory subjects that the search
+  // does not model, so this is the best timetable found but may not be optimal
+  BestFound,
+  // The search finished without finding any timetable that meets the requirements
+  Infeasible,
+}
+
+// Branch and bound over which slot each student takes each of their subjects in, checking
+// that every subject in every slot can be split into classes within its departments' sizes
+// and classroom counts. Timetables are ranked by the unmet request penalty of the schedule's
+// objective weights. Suited to small cohorts, as the search is exponential in the worst case.
+// Room capacities and features and compulsory subjects are not part of the search, so the
+// status of a school with rooms or compulsory subjects is never better than BestFound.
+#[derive(Debug, Clone)]
+pub struct ExactSolver {
+  pub time_limit: Duration,
+  // Only accept timetables where every student gets every subject they requested
+  pub require_full_placement: bool,
+  // Outcome of the most recent solve
+  pub status: Option<ExactStatus>,
+}
+
+impl ExactSolver {
+  pub fn new(time_limit: Duration) -> Self {
+    Self {
+      time_limit,
+      require_full_placement: false,
+      status: None,
+    }
+  }
+}
+
+impl Default for ExactSolver {
+  fn default() -> Self {
+    Self::new(Duration::from_secs(10))
+  }
+}
+
+impl Solver for ExactSolver {
+  fn name(&self) -> String {
+    "Exact branch and bound".to_string()
+  }
+  fn solve(&mut self, schedule: &Schedule) -> Result<Timetable> {
+    self.status = None;
+    // The search assigns each request to a single slot
+    if schedule.subject_list.iter().any(|x| x.slot_demand > 1) {
+      return Err(Error::Generic(
+        "Exact solver does not support subjects that take more than one slot".to_string(),
+      ));
+    }
+    let slot_count = schedule.slot_count();
+    let mut search = Search::new(schedule, slot_count, self.require_full_placement);
+    search.deadline = Instant::now() + self.time_limit;
+
+    // The greedy timetable gives a first solution to bound the search with
+    let greedy_timetable = GreedySolver::new().solve(schedule)?;
+    let greedy_placed = greedy_timetable.satisfied_request_count(&schedule.student_list);
+    if !self.require_full_placement || greedy_placed == search.requested_count {
+      search.best_value = Some(
+        search.requested_value
+          - greedy_timetable
+            .unmet_request_penalty(&schedule.student_list, &schedule.objective_weights),
+      );
+    }
+
+    search.search_student(0);
+
+    let is_modelled = schedule.room_list.is_empty()
+      && !schedule.student_list.iter().any(|student| {
+        student
+          .subject_list
+          .iter()
+          .any(|subject| student.is_compulsory(subject))
+      });
+    self.status = Some(match (search.timed_out, search.best_value) {
+      (false, Some(_)) if is_modelled => ExactStatus::Optimal,
+      (false, Some(_)) => ExactStatus::BestFound,
+      (true, Some(_)) => ExactStatus::BestFound,
+      (false, None) => ExactStatus::Infeasible,
+      (true, None) => {
+        return Err(Error::Generic(
+          "No feasible timetable found within the time limit".to_string(),
+        ))
+      }
+    });
+
+    match search.best_assignment.take() {
+      Some(assignment) => search.build_timetable(schedule, &assignment),
+      None if search.best_value.is_some() => Ok(greedy_timetable),
+      None => Err(Error::Generic(
+        "No timetable places every requested subject".to_string(),
+      )),
+    }
+  }
+}
+
+struct Search {
+  slot_count: usize,
+  require_full_placement: bool,
+  // Per department, indexed like Schedule::department_list
+  min_class_size: Vec<usize>,
+  max_class_size: Vec<usize>,
+  class_count: Vec<usize>,
+  // Departments using each room, empty when the school has no rooms
+  room_department_list: Vec<Vec<usize>>,
+  // Per subject, indexed like Schedule::subject_list
+  subject_department_list: Vec<Vec<usize>>,
+  // Per student, indexed like Schedule::student_list
+  student_subject_list: Vec<Vec<usize>>,
+  // Weight of each of the student's requests, in the order of student_subject_list
+  request_value_list: Vec<Vec<f64>>,
+  // Whether each student can be taught in each slot, which depends on their cohort
+  student_slot_list: Vec<Vec<bool>>,
+  // Slots taught to the same cohorts share a kind, numbered by the first slot of that kind
+  slot_kind: Vec<usize>,
+  // Order the students are searched in
+  student_order: Vec<usize>,
+  // Upper bound on the value of the placements possible from each position in the order onwards
+  suffix_bound: Vec<f64>,
+  requested_count: usize,
+  requested_value: f64,
+
+  // Number of students taking each subject in each slot
+  group_size: Vec<Vec<usize>>,
+  // Number of students in each slot across all subjects
+  slot_size: Vec<usize>,
+  // Students later in the order that requested each subject
+  remaining_requests: Vec<usize>,
+  assignment: Vec<Vec<Option<usize>>>,
+  // Total weight of the requests placed so far
+  placed_value: f64,
+
+  best_value: Option<f64>,
+  best_assignment: Option<Vec<Vec<Option<usize>>>>,
+  deadline: Instant,
+  timed_out: bool,
+}
+
+impl Search {
+  fn new(schedule: &Schedule, slot_count: usize, require_full_placement: bool) -> Self {
+    let department_index = |department: &std::sync::Weak<crate::Department>| {
+      schedule
+        .department_list
+        .iter()
+        .position(|x| department.ptr_eq(&Arc::downgrade(x)))
+        .unwrap()
+    };
+    let subject_department_list: Vec<Vec<usize>> = schedule
+      .subject_list
+      .iter()
+      .map(|subject| subject.department_list.iter().map(department_index).collect())
+      .collect();
+    let student_subject_list: Vec<Vec<usize>> = schedule
+      .student_list
+      .iter()
+      .map(|student| {
+        let mut subject_list = Vec::new();
+        for subject in &student.subject_list {
+          let index = schedule
+            .subject_list
+            .iter()
+            .position(|x| subject.ptr_eq(&Arc::downgrade(x)))
+            .unwrap();
+          if !subject_list.contains(&index) {
+            subject_list.push(index);
+          }
+        }
+        subject_list
+      })
+      .collect();
+
+    let student_slot_list: Vec<Vec<bool>> = schedule
+      .student_list
+      .iter()
+      .map(|student| {
+        (0..slot_count)
+          .map(|slot| student.uses_slot(slot))
+          .collect()
+      })
+      .collect();
+    let slot_cohort_list: Vec<Vec<bool>> = (0..slot_count)
+      .map(|slot| {
+        schedule
+          .cohort_list
+          .iter()
+          .map(|cohort| cohort.uses_slot(slot))
+          .collect()
+      })
+      .collect();
+    let slot_kind: Vec<usize> = slot_cohort_list
+      .iter()
+      .map(|cohort_list| {
+        slot_cohort_list
+          .iter()
+          .position(|other| other == cohort_list)
+          .unwrap()
+      })
+      .collect();
+
+    // Students with the most subjects are the most constrained, so search them first
+    let mut student_order: Vec<usize> = (0..student_subject_list.len()).collect();
+    student_order.sort_by_key(|&x| std::cmp::Reverse(student_subject_list[x].len()));
+
+    let request_value_list: Vec<Vec<f64>> = schedule
+      .student_list
+      .iter()
+      .zip(&student_subject_list)
+      .map(|(student, subject_list)| {
+        subject_list
+          .iter()
+          .map(|&subject| {
+            schedule
+              .objective_weights
+              .unmet_request_weight(student, &Arc::downgrade(&schedule.subject_list[subject]))
+          })
+          .collect()
+      })
+      .collect();
+
+    // A student can at best place their most valuable requests, one per slot open to them
+    let mut suffix_bound = vec![0.0; student_order.len() + 1];
+    for position in (0..student_order.len()).rev() {
+      let student = student_order[position];
+      let open_slot_count = student_slot_list[student].iter().filter(|&&x| x).count();
+      let mut value_list = request_value_list[student].clone();
+      value_list.sort_by(|x, y| y.total_cmp(x));
+      suffix_bound[position] =
+        suffix_bound[position + 1] + value_list.iter().take(open_slot_count).sum::<f64>();
+    }
+
+    let mut remaining_requests = vec![0; schedule.subject_list.len()];
+    for subject_list in &student_subject_list {
+      for &subject in subject_list {
+        remaining_requests[subject] += 1;
+      }
+    }
+
+    Self {
+      slot_count,
+      require_full_placement,
+      min_class_size: schedule
+        .department_list
+        .iter()
+        .map(|x| x.min_class_size)
+        .collect(),
+      max_class_size: schedule
+        .department_list
+        .iter()
+        .map(|x| x.max_class_size)
+        .collect(),
+      class_count: schedule
+        .department_list
+        .iter()
+        .map(|x| schedule.department_room_count(x))
+        .collect(),
+      room_department_list: schedule.room_department_list(),
+      requested_count: student_subject_list.iter().map(|x| x.len()).sum(),
+      requested_value: request_value_list.iter().flatten().sum(),
+      assignment: student_subject_list
+        .iter()
+        .map(|x| vec![None; x.len()])
+        .collect(),
+      group_size: vec![vec![0; slot_count]; schedule.subject_list.len()],
+      slot_size: vec![0; slot_count],
+      subject_department_list,
+      student_subject_list,
+      request_value_list,
+      student_slot_list,
+      slot_kind,
+      student_order,
+      suffix_bound,
+      remaining_requests,
+      placed_value: 0.0,
+      best_value: None,
+      best_assignment: None,
+      deadline: Instant::now(),
+      timed_out: false,
+    }
+  }
+
+  fn search_student(&mut self, position: usize) {
+    if self.timed_out {
+      return;
+    }
+    if Instant::now() > self.deadline {
+      self.timed_out = true;
+      return;
+    }
+    if position == self.student_order.len() {
+      if self
+        .best_value
+        .is_none_or(|best| self.placed_value > best + VALUE_TOLERANCE)
+      {
+        self.best_value = Some(self.placed_value);
+        self.best_assignment = Some(self.assignment.clone());
+      }
+      return;
+    }
+
+    let student = self.student_order[position];
+    let subject_count = self.student_subject_list[student].len();
+    for subject in self.student_subject_list[student].clone() {
+      self.remaining_requests[subject] -= 1;
+    }
+
+    let max_drop_count = if self.require_full_placement {
+      0
+    } else {
+      subject_count
+    };
+    let open_slot_count = self.student_slot_list[student]
+      .iter()
+      .filter(|&&x| x)
+      .count();
+    // At best the student drops their least valuable requests
+    let mut value_list = self.request_value_list[student].clone();
+    value_list.sort_by(|x, y| x.total_cmp(y));
+    let student_value: f64 = value_list.iter().sum();
+
+    // Placing as many subjects as possible first finds good solutions early
+    for drop_count in subject_count.saturating_sub(open_slot_count)..=max_drop_count {
+      if let Some(best) = self.best_value {
+        let dropped_value: f64 = value_list[..drop_count].iter().sum();
+        if self.placed_value + student_value - dropped_value + self.suffix_bound[position + 1]
+          <= best + VALUE_TOLERANCE
+        {
+          break;
+        }
+      }
+      let mut used_slots = vec![false; self.slot_count];
+      self.search_subject(position, 0, drop_count, &mut used_slots);
+      if self.timed_out {
+        break;
+      }
+    }
+
+    for subject in self.student_subject_list[student].clone() {
+      self.remaining_requests[subject] += 1;
+    }
+  }
+
+  fn search_subject(
+    &mut self,
+    position: usize,
+    subject_index: usize,
+    drop_count: usize,
+    used_slots: &mut [bool],
+  ) {
+    let student = self.student_order[position];
+    let subject_list = &self.student_subject_list[student];
+    if subject_index == subject_list.len() {
+      if drop_count == 0 && self.student_slots_feasible(student) {
+        self.search_student(position + 1);
+      }
+      return;
+    }
+    let subject = subject_list[subject_index];
+    let subjects_left = subject_list.len() - subject_index;
+
+    if drop_count < subjects_left {
+      // Join existing groups of the subject before opening new ones
+      let mut slot_order: Vec<usize> = (0..self.slot_count).collect();
+      slot_order.sort_by_key(|&slot| std::cmp::Reverse(self.group_size[subject][slot]));
+
+      // Empty slots taught to the same cohorts are interchangeable, so only the first one of
+      // each kind needs to be tried
+      let mut tried_empty_kind = vec![false; self.slot_count];
+      for slot in slot_order {
+        if used_slots[slot] || !self.student_slot_list[student][slot] {
+          continue;
+        }
+        if self.slot_size[slot] == 0 {
+          if tried_empty_kind[self.slot_kind[slot]] {
+            continue;
+          }
+          tried_empty_kind[self.slot_kind[slot]] = true;
+        }
+        if self.group_size[subject][slot] + 1 > self.subject_capacity(subject) {
+          continue;
+        }
+        self.group_size[subject][slot] += 1;
+        self.slot_size[slot] += 1;
+        self.placed_value += self.request_value_list[student][subject_index];
+        self.assignment[student][subject_index] = Some(slot);
+        used_slots[slot] = true;
+
+        self.search_subject(position, subject_index + 1, drop_count, used_slots);
+
+        used_slots[slot] = false;
+        self.assignment[student][subject_index] = None;
+        self.placed_value -= self.request_value_list[student][subject_index];
+        self.slot_size[slot] -= 1;
+        self.group_size[subject][slot] -= 1;
+        if self.timed_out {
+          return;
+        }
+      }
+    }
+
+    if drop_count > 0 {
+      self.search_subject(position, subject_index + 1, drop_count - 1, used_slots);
+    }
+  }
+
+  // The most students that could take the subject in one slot
+  fn subject_capacity(&self, subject: usize) -> usize {
+    self.subject_department_list[subject]
+      .iter()
+      .map(|&department| self.class_count[department] * self.max_class_size[department])
+      .sum()
+  }
+
+  // Re-checks every slot the student's subjects are taught in, since both the students in
+  // them and the students still to come may have changed
+  fn student_slots_feasible(&self, student: usize) -> bool {
+    // Later students are shared between all the slots a subject is taught in, so together
+    // they must be able to bring every group up to the smallest minimum class size
+    for &subject in &self.student_subject_list[student] {
+      let minimum = self.subject_department_list[subject]
+        .iter()
+        .map(|&department| self.min_class_size[department])
+        .min()
+        .unwrap();
+      let shortfall: usize = self.group_size[subject]
+        .iter()
+        .filter(|&&size| size > 0)
+        .map(|&size| minimum.saturating_sub(size))
+        .sum();
+      if shortfall > self.remaining_requests[subject] {
+        return false;
+      }
+    }
+    (0..self.slot_count).all(|slot| {
+      !self.student_subject_list[student]
+        .iter()
+        .any(|&subject| self.group_size[subject][slot] > 0)
+        || self.slot_classes(slot).is_some()
+    })
+  }
+
+  // Finds how many classes each department runs for each subject in the slot. Groups that
+  // later students may still join only need to be able to reach the minimum class size.
+  fn slot_classes(&self, slot: usize) -> Option<Vec<(usize, Vec<usize>)>> {
+    let group_list: Vec<usize> = (0..self.group_size.len())
+      .filter(|&subject| self.group_size[subject][slot] > 0)
+      .collect();
+    let mut department_usage = vec![0; self.class_count.len()];
+    let mut chosen = Vec::new();
+    if self.fit_group(slot, &group_list, &mut department_usage, &mut chosen) {
+      Some(group_list.into_iter().zip(chosen).collect())
+    } else {
+      None
+    }
+  }
+
+  fn fit_group(
+    &self,
+    slot: usize,
+    group_list: &[usize],
+    department_usage: &mut [usize],
+    chosen: &mut Vec<Vec<usize>>,
+  ) -> bool {
+    // Departments sharing rooms can only run as many classes together as there are rooms
+    let Some(&subject) = group_list.get(chosen.len()) else {
+      return self.room_department_list.is_empty()
+        || rooms_fit(&self.room_department_list, department_usage);
+    };
+    self.fit_department(
+      slot,
+      subject,
+      0,
+      (0, 0),
+      group_list,
+      department_usage,
+      &mut Vec::new(),
+      chosen,
+    )
+  }
+
+  // Chooses the number of classes for each of the subject's departments in turn, stopping as
+  // soon as they can hold every student, since more classes only make the minimum harder
+  #[allow(clippy::too_many_arguments)]
+  fn fit_department(
+    &self,
+    slot: usize,
+    subject: usize,
+    department_index: usize,
+    (capacity, minimum): (usize, usize),
+    group_list: &[usize],
+    department_usage: &mut [usize],
+    class_counts: &mut Vec<usize>,
+    chosen: &mut Vec<Vec<usize>>,
+  ) -> bool {
+    let size = self.group_size[subject][slot];
+    if capacity >= size {
+      if minimum > size + self.remaining_requests[subject] {
+        return false;
+      }
+      let mut counts = class_counts.clone();
+      counts.resize(self.subject_department_list[subject].len(), 0);
+      chosen.push(counts);
+      if self.fit_group(slot, group_list, department_usage, chosen) {
+        return true;
+      }
+      chosen.pop();
+      return false;
+    }
+    let Some(&department) = self.subject_department_list[subject].get(department_index) else {
+      return false;
+    };
+    let available = self.class_count[department] - department_usage[department];
+    for count in 0..=available {
+      let next_minimum = minimum + count * self.min_class_size[department];
+      if next_minimum > size + self.remaining_requests[subject] {
+        break;
+      }
+      department_usage[department] += count;
+      class_counts.push(count);
+      let fitted = self.fit_department(
+        slot,
+        subject,
+        department_index + 1,
+        (
+          capacity + count * self.max_class_size[department],
+          next_minimum,
+        ),
+        group_list,
+        department_usage,
+        class_counts,
+        chosen,
+      );
+      class_counts.pop();
+      department_usage[department] -= count;
+      if fitted {
+        return true;
+      }
+    }
+    false
+  }
+
+  fn build_timetable(
+    &self,
+    schedule: &Schedule,
+    assignment: &[Vec<Option<usize>>],
+  ) -> Result<Timetable> {
+    let mut search = Search::new(schedule, self.slot_count, self.require_full_placement);
+    for (student, slot_list) in assignment.iter().enumerate() {
+      for (subject_index, slot) in slot_list.iter().enumerate() {
+        if let Some(slot) = slot {
+          let subject = search.student_subject_list[student][subject_index];
+          search.group_size[subject][*slot] += 1;
+        }
+      }
+    }
+    search.remaining_requests.iter_mut().for_each(|x| *x = 0);
+
+    let timetable = schedule.empty_timetable();
+    for slot in 0..self.slot_count {
+      let Some(slot_classes) = search.slot_classes(slot) else {
+        return Err(Error::Generic(format!(
+          "Classes of slot {} cannot be split between departments within their sizes",
+          slot
+        )));
+      };
+      let mut slot_lock = timetable.slot_list[slot].lock().unwrap();
+      for (subject, class_counts) in slot_classes {
+        let mut student_list = Vec::new();
+        for (student, slot_list) in assignment.iter().enumerate() {
+          for (subject_index, student_slot) in slot_list.iter().enumerate() {
+            if *student_slot == Some(slot) && search.student_subject_list[student][subject_index] == subject {
+              student_list.push(Arc::downgrade(&schedule.student_list[student]));
+            }
+          }
+        }
+
+        // Start every class at its minimum size and fill them up to their maximum in turn
+        let mut class_list = Vec::new();
+        for (department_index, &count) in class_counts.iter().enumerate() {
+          let department = search.subject_department_list[subject][department_index];
+          for _ in 0..count {
+            class_list.push((department, self.min_class_size[department]));
+          }
+        }
+        let mut extra = student_list.len() - class_list.iter().map(|x| x.1).sum::<usize>();
+        for (department, size) in &mut class_list {
+          let added = extra.min(self.max_class_size[*department] - *size);
+          *size += added;
+          extra -= added;
+        }
+
+        for (department, size) in class_list {
+          slot_lock.class_list.push(Class::new(
+            Arc::downgrade(&schedule.subject_list[subject]),
+            Arc::downgrade(&schedule.department_list[department]),
+            student_list.drain(..size).collect(),
+          ));
+        }
+      }
+    }
+    Ok(timetable)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn small_school() -> Schedule {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 2, 3, 1).unwrap();
+    high_school.new_department("Science", 2, 3, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Statistics", vec!["Maths"]).unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Physics"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Calculus", "Physics"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Three", "3", vec!["Statistics", "Physics"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Four", "4", vec!["Statistics", "Calculus"])
+      .unwrap();
+    high_school
+  }
+
+  #[test]
+  fn test_exact_solver_optimal() {
+    let mut high_school = small_school();
+    let mut solver = ExactSolver::default();
+    let report = high_school.sort_with(&mut solver).unwrap();
+
+    assert_eq!(solver.status, Some(ExactStatus::Optimal));
+    assert_eq!(report.placed_count(), report.requested_count());
+    for slot in &high_school.timetable.slot_list {
+      let slot = slot.lock().unwrap();
+      for class in &slot.class_list {
+        let department = class.department.upgrade().unwrap();
+        assert!(class.student_list.len() >= department.min_class_size);
+        assert!(class.student_list.len() <= department.max_class_size);
+      }
+    }
+  }
+
+  #[test]
+  fn test_exact_solver_infeasible() {
+    let mut high_school = small_school();
+    high_school.new_department("Art", 3, 30, 10).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "Five", "5", vec!["Painting"])
+      .unwrap();
+
+    let mut solver = ExactSolver {
+      require_full_placement: true,
+      ..Default::default()
+    };
+    assert!(high_school.sort_with(&mut solver).is_err());
+    assert_eq!(solver.status, Some(ExactStatus::Infeasible));
+
+    solver.require_full_placement = false;
+    let report = high_school.sort_with(&mut solver).unwrap();
+    assert_eq!(solver.status, Some(ExactStatus::Optimal));
+    assert_eq!(report.placed_count(), report.requested_count() - 1);
+  }
+
+  #[test]
+  fn test_exact_solver_cohorts() {
+    let mut high_school = Schedule::with_slot_count(3);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school.new_department("Languages", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Latin", vec!["Languages"]).unwrap();
+    high_school
+      .new_cohort("Year 12", vec!["Calculus", "Latin"], vec![0, 1])
+      .unwrap();
+    high_school
+      .new_cohort("Year 13", vec!["Latin"], vec![1, 2])
+      .unwrap();
+    high_school
+      .new_student_in_cohort("Person", "One", "1", "Year 12", vec!["Calculus", "Latin"])
+      .unwrap();
+    high_school
+      .new_student_in_cohort("Person", "Two", "2", "Year 13", vec!["Latin"])
+      .unwrap();
+
+    let mut solver = ExactSolver {
+      require_full_placement: true,
+      ..Default::default()
+    };
+    let report = high_school.sort_with(&mut solver).unwrap();
+    assert_eq!(report.placed_count(), 3);
+    // Latin can only be shared in the one slot both year levels are taught in
+    let slot = high_school.timetable.slot_list[1].lock().unwrap();
+    assert_eq!(slot.class_list.len(), 1);
+    assert_eq!(slot.class_list[0].student_list.len(), 2);
+  }
+
+  #[test]
+  fn test_exact_solver_shared_room() {
+    let mut high_school = Schedule::with_slot_count(1);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school.new_department("Science", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school
+      .new_room("R1", 30, vec!["Maths", "Science"], Vec::<String>::new())
+      .unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Physics"])
+      .unwrap();
+
+    // Both departments can use the room, but only one class fits in it at a time
+    assert!(!high_school.rooms_fit(&[1, 1]));
+    let mut solver = ExactSolver::default();
+    let report = high_school.sort_with(&mut solver).unwrap();
+    // Rooms are only counted, not matched by capacity and features, so this may not be optimal
+    assert_eq!(solver.status, Some(ExactStatus::BestFound));
+    assert_eq!(report.placed_count(), 1);
+  }
+
+  #[test]
+  fn test_exact_solver_compulsory_is_best_found() {
+    let mut high_school = Schedule::with_slot_count(1);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school
+      .new_compulsory_subject("Calculus", vec!["Maths"])
+      .unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus"])
+      .unwrap();
+
+    let mut solver = ExactSolver::default();
+    let report = high_school.sort_with(&mut solver).unwrap();
+    assert_eq!(solver.status, Some(ExactStatus::BestFound));
+    assert_eq!(report.placed_count(), 1);
+  }
+
+  #[test]
+  fn test_exact_solver_ranks() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 2, 30, 1).unwrap();
+    high_school.new_department("Art", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school.new_subject("Drawing", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Drawing", "Calculus"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Painting", "Calculus"])
+      .unwrap();
+    // Missing a first choice costs more than missing two last choices
+    high_school.objective_weights.unmet_rank = 200.0;
+
+    // Art runs one class a slot, so either both take Calculus together and one misses their
+    // art class, or neither takes Calculus
+    let mut solver = ExactSolver::default();
+    high_school.sort_with(&mut solver).unwrap();
+    assert_eq!(solver.status, Some(ExactStatus::Optimal));
+    let score = high_school
+      .timetable
+      .score(&high_school.student_list, &high_school.objective_weights);
+    assert_eq!(score.unmet_request_count, 2);
+    assert_eq!(score.unmet_rank, 0);
+  }
+
+  #[test]
+  fn test_exact_solver_group_between_class_sizes() {
+    let mut high_school = Schedule::with_slot_count(1);
+    high_school.new_department("A", 5, 10, 1).unwrap();
+    high_school.new_department("B", 1, 2, 1).unwrap();
+    high_school.new_subject("Z", vec!["A", "B"]).unwrap();
+    for id in ["1", "2", "3"] {
+      high_school
+        .new_student("Person", id, id, vec!["Z"])
+        .unwrap();
+    }
+
+    // A group of 3 is too small for A and too big for B, and both together need at least 6
+    let mut solver = ExactSolver::default();
+    let report = high_school.sort_with(&mut solver).unwrap();
+    assert_eq!(solver.status, Some(ExactStatus::Optimal));
+    assert_eq!(report.placed_count(), 2);
+  }
+}
@@ -0,0 +1,24 @@
+use super::Solver;
+use crate::{prelude::*, timetable::Timetable, Schedule};
+
+// Adds students in order to their most constrained subject first, then removes the classes
+// that are too small and re-places the students they displaced
+#[derive(Debug, Default, Clone)]
+pub struct GreedySolver {}
+
+impl GreedySolver {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl Solver for GreedySolver {
+  fn name(&self) -> String {
+    "Greedy".to_string()
+  }
+  fn solve(&mut self, schedule: &Schedule) -> Result<Timetable> {
+    let mut timetable = schedule.empty_timetable();
+    timetable.sort_students(&schedule.student_list)?;
+    Ok(timetable)
+  }
+}
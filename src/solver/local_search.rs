@@ -0,0 +1,39 @@
+use super::Solver;
+use crate::{prelude::*, timetable::Timetable, AnnealConfig, AnnealReport, Schedule};
+
+// Runs the greedy sort, then improves it with simulated annealing
+#[derive(Debug, Default, Clone)]
+pub struct LocalSearchSolver {
+  pub config: AnnealConfig,
+  // Objective before and after annealing, from the most recent solve
+  pub anneal_report: Option<AnnealReport>,
+}
+
+impl LocalSearchSolver {
+  pub fn new(config: AnnealConfig) -> Self {
+    Self {
+      config,
+      anneal_report: None,
+    }
+  }
+}
+
+impl Solver for LocalSearchSolver {
+  fn name(&self) -> String {
+    "Local search".to_string()
+  }
+  fn solve(&mut self, schedule: &Schedule) -> Result<Timetable> {
+    let mut timetable = schedule.empty_timetable();
+    timetable.sort_students(&schedule.student_list)?;
+    self.anneal_report = Some(timetable.anneal(
+      &schedule.student_list,
+      &self.config,
+      &schedule.objective_weights,
+      &mut schedule.rng(),
+    ));
+    Ok(timetable)
+  }
+  fn anneal_report(&self) -> Option<AnnealReport> {
+    self.anneal_report
+  }
+}
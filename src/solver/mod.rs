@@ -0,0 +1,54 @@
+//! Strategies for sorting the students of a schedule into a timetable
+
+mod blocking;
+mod exact;
+mod greedy;
+mod local_search;
+mod multi_start;
+mod slot_search;
+
+use crate::{prelude::*, timetable::Timetable, AnnealReport, Schedule};
+pub use blocking::{
+  alternative_combinations, BlockDesigner, BlockEntry, BlockSolver, BlockingPlan,
+};
+pub use exact::{ExactSolver, ExactStatus};
+pub use greedy::GreedySolver;
+pub use local_search::LocalSearchSolver;
+pub use multi_start::MultiStartSolver;
+pub use slot_search::{SlotSearch, SlotSearchCandidate, SlotSearchReport};
+
+pub trait Solver {
+  fn name(&self) -> String;
+  // Builds a timetable for the students, subjects and departments of the schedule, using as
+  // many slots as the schedule's timetable has
+  fn solve(&mut self, schedule: &Schedule) -> Result<Timetable>;
+  // Objective before and after the most recent solve's annealing pass, for solvers with one
+  fn anneal_report(&self) -> Option<AnnealReport> {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{random, AnnealConfig};
+  use std::time::Duration;
+
+  #[test]
+  fn test_solvers_on_same_schedule() {
+    let mut high_school = random::random_schedule(150, 10, 40, 8);
+    let mut solver_list: Vec<Box<dyn Solver>> = vec![
+      Box::new(GreedySolver::new()),
+      Box::new(MultiStartSolver::new(3)),
+      Box::new(LocalSearchSolver::new(AnnealConfig {
+        time_limit: Duration::from_millis(100),
+        ..Default::default()
+      })),
+    ];
+    for solver in &mut solver_list {
+      let report = high_school.sort_with(solver.as_mut()).unwrap();
+      assert_eq!(high_school.timetable.slot_list.len(), 5);
+      assert_eq!(report.requested_count(), 750);
+    }
+  }
+}
@@ -0,0 +1,47 @@
+use super::Solver;
+use crate::{prelude::*, timetable::Timetable, Schedule};
+use rand::seq::SliceRandom;
+
+// Runs the greedy sort over several random student orders and keeps the timetable with the
+// best score under the schedule's objective weights
+#[derive(Debug, Clone)]
+pub struct MultiStartSolver {
+  pub start_count: usize,
+}
+
+impl MultiStartSolver {
+  pub fn new(start_count: usize) -> Self {
+    Self { start_count }
+  }
+}
+
+impl Default for MultiStartSolver {
+  fn default() -> Self {
+    Self::new(10)
+  }
+}
+
+impl Solver for MultiStartSolver {
+  fn name(&self) -> String {
+    format!("Multi-start ({} starts)", self.start_count)
+  }
+  fn solve(&mut self, schedule: &Schedule) -> Result<Timetable> {
+    let mut rng = schedule.rng();
+    let mut student_list = schedule.student_list.clone();
+    let mut best: Option<(f64, Timetable)> = None;
+
+    for _ in 0..self.start_count.max(1) {
+      let mut timetable = schedule.empty_timetable();
+      timetable.sort_students(&student_list)?;
+      let objective = timetable
+        .score(&schedule.student_list, &schedule.objective_weights)
+        .total;
+      if best.as_ref().is_none_or(|(best_objective, _)| objective < *best_objective) {
+        best = Some((objective, timetable));
+      }
+      student_list.shuffle(&mut rng);
+    }
+
+    Ok(best.unwrap().1)
+  }
+}
@@ -0,0 +1,282 @@
+//! Searching for the fewest slots, and classrooms, a school can run with
+
+use super::Solver;
+use crate::{prelude::*, Schedule, SortReport};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct SlotSearch {
+  // Defaults to the most slots any student's subjects take
+  pub min_slot_count: Option<usize>,
+  pub max_slot_count: usize,
+  // Fraction of requested subjects, from 0 to 1, a candidate must place
+  pub target_placement_rate: f64,
+  // Other per-department class counts to try at each slot count, besides the school's own
+  pub class_count_option_list: Vec<Vec<(String, usize)>>,
+}
+
+pub struct SlotSearchCandidate {
+  pub slot_count: usize,
+  // Departments whose class count differs from the school's own
+  pub class_count_list: Vec<(String, usize)>,
+  // The school as configured for the candidate, sorted
+  pub schedule: Schedule,
+  pub report: SortReport,
+}
+
+impl SlotSearchCandidate {
+  // Classes the school could run at once across every department
+  pub fn classroom_count(&self) -> usize {
+    self.schedule.classroom_count()
+  }
+}
+
+pub struct SlotSearchReport {
+  pub target_placement_rate: f64,
+  // Ordered by slot count, then by class count option
+  pub candidate_list: Vec<SlotSearchCandidate>,
+  // Index of the candidate with the fewest slots, then the fewest classrooms, that meets the
+  // target placement rate
+  pub best: Option<usize>,
+  // Slot counts the school could not be laid out over, such as when a cohort is left with
+  // fewer slots than its students' subjects take, or that could not seat every compulsory
+  // subject, with why
+  pub skipped_list: Vec<(usize, String)>,
+}
+
+impl SlotSearchReport {
+  pub fn best_candidate(&self) -> Option<&SlotSearchCandidate> {
+    self.best.map(|index| &self.candidate_list[index])
+  }
+}
+
+impl fmt::Display for SlotSearchReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Target placement rate: {:.1}%",
+      self.target_placement_rate * 100.0
+    )?;
+    for (index, candidate) in self.candidate_list.iter().enumerate() {
+      write!(
+        f,
+        "\n{} {} slots, {} classrooms",
+        if self.best == Some(index) { "*" } else { " " },
+        candidate.slot_count,
+        candidate.classroom_count()
+      )?;
+      for (name, class_count) in &candidate.class_count_list {
+        write!(f, ", {}: {}", name, class_count)?;
+      }
+      write!(
+        f,
+        ": placed {:.1}%, {}",
+        candidate.report.placement_rate() * 100.0,
+        candidate.report.score
+      )?;
+    }
+    for (slot_count, reason) in &self.skipped_list {
+      write!(f, "\n  {} slots skipped: {}", slot_count, reason)?;
+    }
+    Ok(())
+  }
+}
+
+impl SlotSearch {
+  pub fn new(max_slot_count: usize, target_placement_rate: f64) -> Self {
+    Self {
+      min_slot_count: None,
+      max_slot_count,
+      target_placement_rate,
+      class_count_option_list: Vec::new(),
+    }
+  }
+
+  // Sorts a copy of the school with every slot count in range and every class count option
+  pub fn run(&self, schedule: &Schedule, solver: &mut dyn Solver) -> Result<SlotSearchReport> {
+    if !(0.0..=1.0).contains(&self.target_placement_rate) {
+      return Err(Error::Generic(format!(
+        "Target placement rate ({}) is outside of 0 to 1",
+        self.target_placement_rate
+      )));
+    }
+    // Fewer slots than a student's subjects take cannot even hold their choices
+    let min_slot_count = self.min_slot_count.unwrap_or_else(|| {
+      schedule
+        .student_list
+        .iter()
+        .map(|x| x.slot_demand())
+        .max()
+        .unwrap_or(1)
+        .max(1)
+    });
+
+    let mut class_count_option_list = vec![Vec::new()];
+    class_count_option_list.extend(self.class_count_option_list.iter().cloned());
+
+    let mut report = SlotSearchReport {
+      target_placement_rate: self.target_placement_rate,
+      candidate_list: Vec::new(),
+      best: None,
+      skipped_list: Vec::new(),
+    };
+    for slot_count in min_slot_count..=self.max_slot_count {
+      for class_count_list in &class_count_option_list {
+        let mut candidate_schedule = match schedule.reconfigured(slot_count, class_count_list) {
+          Ok(k) => k,
+          Err(e) => {
+            report.skipped_list.push((slot_count, e.to_string()));
+            continue;
+          }
+        };
+        // Configurations that cannot seat every compulsory subject are not candidates
+        let sort_report = match candidate_schedule.sort_with(solver) {
+          Err(e @ Error::CompulsoryUnmet(_)) => {
+            report.skipped_list.push((slot_count, e.to_string()));
+            continue;
+          }
+          sort_report => sort_report?,
+        };
+        let candidate = SlotSearchCandidate {
+          slot_count,
+          class_count_list: class_count_list.clone(),
+          schedule: candidate_schedule,
+          report: sort_report,
+        };
+
+        let is_better = candidate.report.placement_rate() >= self.target_placement_rate
+          && report.best_candidate().is_none_or(|best| {
+            (candidate.slot_count, candidate.classroom_count())
+              < (best.slot_count, best.classroom_count())
+          });
+        report.candidate_list.push(candidate);
+        if is_better {
+          report.best = Some(report.candidate_list.len() - 1);
+        }
+      }
+    }
+    Ok(report)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::GreedySolver;
+
+  fn school() -> Schedule {
+    // Every student takes every subject, so three slots are needed
+    let mut high_school = Schedule::with_slot_count(5);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    for name in ["Calculus", "Statistics", "Algebra"] {
+      high_school.new_subject(name, vec!["Maths"]).unwrap();
+    }
+    for id in ["1", "2", "3"] {
+      high_school
+        .new_student("Person", id, id, vec!["Calculus", "Statistics", "Algebra"])
+        .unwrap();
+    }
+    high_school
+  }
+
+  #[test]
+  fn test_slot_search() {
+    let high_school = school();
+    let report = SlotSearch::new(5, 1.0)
+      .run(&high_school, &mut GreedySolver::new())
+      .unwrap();
+
+    assert_eq!(report.candidate_list.len(), 3);
+    assert_eq!(report.candidate_list[0].slot_count, 3);
+    assert_eq!(report.best_candidate().unwrap().slot_count, 3);
+    // The original school is left as it was
+    assert_eq!(high_school.slot_count(), 5);
+  }
+
+  #[test]
+  fn test_slot_search_with_class_counts() {
+    let high_school = school();
+    let report = SlotSearch {
+      class_count_option_list: vec![vec![("Maths".to_string(), 3)]],
+      ..SlotSearch::new(4, 1.0)
+    }
+    .run(&high_school, &mut GreedySolver::new())
+    .unwrap();
+
+    assert_eq!(report.candidate_list.len(), 4);
+    // More classrooms do not help when there are already enough, so the fewest win
+    let best = report.best_candidate().unwrap();
+    assert_eq!(best.slot_count, 3);
+    assert!(best.class_count_list.is_empty());
+    assert_eq!(best.classroom_count(), 1);
+  }
+
+  #[test]
+  fn test_slot_search_cohorts() {
+    let mut high_school = Schedule::with_slot_count(4);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject("Statistics", vec!["Maths"])
+      .unwrap();
+    high_school
+      .new_cohort("Year 12", vec!["Calculus", "Statistics"], vec![2, 3])
+      .unwrap();
+    high_school
+      .new_cohort("Year 13", vec!["Calculus"], vec![3])
+      .unwrap();
+    high_school
+      .new_student_in_cohort(
+        "Person",
+        "1",
+        "1",
+        "Year 12",
+        vec!["Calculus", "Statistics"],
+      )
+      .unwrap();
+    high_school
+      .new_student_in_cohort("Person", "2", "2", "Year 13", vec!["Calculus"])
+      .unwrap();
+    let report = SlotSearch {
+      min_slot_count: Some(1),
+      ..SlotSearch::new(8, 1.0)
+    }
+    .run(&high_school, &mut GreedySolver::new())
+    .unwrap();
+
+    // Year 12 needs two slots of its own, which it only keeps from four slots up
+    let skipped_count_list: Vec<usize> = report.skipped_list.iter().map(|x| x.0).collect();
+    assert_eq!(skipped_count_list, vec![1, 2, 3]);
+    let candidate = &report.candidate_list[report.candidate_list.len() - 1];
+    assert_eq!(candidate.slot_count, 8);
+    let year_12 = candidate.schedule.get_cohort("Year 12").unwrap();
+    assert_eq!(year_12.slot_list, vec![4, 5, 6, 7]);
+    let year_13 = candidate.schedule.get_cohort("Year 13").unwrap();
+    assert_eq!(year_13.slot_list, vec![6, 7]);
+    assert!(report.best_candidate().is_some());
+  }
+
+  #[test]
+  fn test_slot_search_records_unmet_compulsory() {
+    let mut high_school = Schedule::with_slot_count(1);
+    // Only one student fits in each slot's class
+    high_school.new_department("Maths", 1, 1, 1).unwrap();
+    high_school
+      .new_compulsory_subject("Calculus", vec!["Maths"])
+      .unwrap();
+    for id in ["1", "2"] {
+      high_school
+        .new_student("Person", id, id, vec!["Calculus"])
+        .unwrap();
+    }
+    let report = SlotSearch::new(2, 1.0)
+      .run(&high_school, &mut GreedySolver::new())
+      .unwrap();
+
+    // A single slot cannot seat both students, so it is skipped rather than dropped silently
+    let skipped_count_list: Vec<usize> = report.skipped_list.iter().map(|x| x.0).collect();
+    assert_eq!(skipped_count_list, vec![1]);
+    assert_eq!(report.candidate_list.len(), 1);
+    assert_eq!(report.best_candidate().unwrap().slot_count, 2);
+  }
+}
@@ -0,0 +1,589 @@
+//! Simulated annealing improvement pass over an already sorted timetable
+
+use super::{Class, ObjectiveWeights, Timetable};
+use crate::{Student, Subject};
+use rand::{seq::SliceRandom, Rng};
+use std::{
+  fmt,
+  sync::{Arc, Weak},
+  time::{Duration, Instant},
+};
+
+// Allowed drift in the running objective, which is kept by adding up changes
+const OBJECTIVE_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone)]
+pub struct AnnealConfig {
+  pub time_limit: Duration,
+  // Stops after this many iterations, or at the time limit if that comes first, and cools by
+  // iteration instead of by time, so that a seeded run that finishes its iterations is
+  // repeatable regardless of how fast it runs
+  pub iteration_limit: Option<usize>,
+  // In points of the schedule's score, so a default unmet request costs 100
+  pub initial_temperature: f64,
+  pub final_temperature: f64,
+}
+
+impl Default for AnnealConfig {
+  fn default() -> Self {
+    Self {
+      time_limit: Duration::from_secs(1),
+      iteration_limit: None,
+      initial_temperature: 200.0,
+      final_temperature: 5.0,
+    }
+  }
+}
+
+// The objective is the unmet request penalty of the timetable's score, where lower is better
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnnealReport {
+  pub objective_before: f64,
+  pub objective_after: f64,
+  // Requested subjects that students have a class for
+  pub placed_before: usize,
+  pub placed_after: usize,
+  pub iteration_count: usize,
+}
+
+impl fmt::Display for AnnealReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Annealing took the unmet request penalty from {:.1} to {:.1}, placing {} requested subjects instead of {}, in {} iterations",
+      self.objective_before,
+      self.objective_after,
+      self.placed_after,
+      self.placed_before,
+      self.iteration_count
+    )
+  }
+}
+
+impl Timetable {
+  pub fn satisfied_request_count(&self, student_list: &[Arc<Student>]) -> usize {
+    student_list
+      .iter()
+      .map(|student| {
+        student
+          .subject_list
+          .len()
+          .saturating_sub(self.unsatisfied_requests(student).len())
+      })
+      .sum()
+  }
+
+  pub fn unsatisfied_requests(&self, student: &Arc<Student>) -> Vec<Weak<Subject>> {
+    let mut unsatisfied = student.subject_list.clone();
+    for slot in &self.slot_list {
+      if let Some(subject) = slot
+        .lock()
+        .unwrap()
+        .get_student_subject(Arc::downgrade(student))
+      {
+        unsatisfied.retain(|x| !x.ptr_eq(&subject));
+      }
+    }
+    unsatisfied
+  }
+
+  // Improves the timetable by moving students between parallel classes and classes between
+  // slots, keeping every class within its department's limits. Runs until the time limit, or
+  // the iteration limit if one is set and reached first, and leaves the timetable in the best
+  // state found. Each unmet request costs its weight, so a first choice outweighs a last one.
+  pub fn anneal(
+    &mut self,
+    student_list: &[Arc<Student>],
+    config: &AnnealConfig,
+    weights: &ObjectiveWeights,
+    rng: &mut impl Rng,
+  ) -> AnnealReport {
+    let start = Instant::now();
+
+    let mut unsatisfied = Vec::<(Weak<Student>, Weak<Subject>)>::new();
+    for student in student_list {
+      for subject in self.unsatisfied_requests(student) {
+        unsatisfied.push((Arc::downgrade(student), subject));
+      }
+    }
+
+    let placed_before = self.satisfied_request_count(student_list);
+    let objective_before = self.unmet_request_penalty(student_list, weights);
+    let (mut objective, mut best_objective) = (objective_before, objective_before);
+    let mut best_class_lists = self.class_lists();
+    let mut iteration_count = 0;
+
+    while self.slot_list.len() > 1 {
+      let time_progress = start.elapsed().as_secs_f64() / config.time_limit.as_secs_f64();
+      let progress = match config.iteration_limit {
+        Some(iteration_limit) => iteration_count as f64 / iteration_limit as f64,
+        None => time_progress,
+      };
+      if progress >= 1.0 || time_progress >= 1.0 {
+        break;
+      }
+      iteration_count += 1;
+      let temperature = config.initial_temperature
+        * (config.final_temperature / config.initial_temperature).powf(progress);
+
+      match rng.gen_range(0..5) {
+        0 => {
+          if let Some(change) = self.try_insert(&mut unsatisfied, weights, rng) {
+            objective += change;
+          }
+        }
+        1 => {
+          if let Some(change) = self.try_eject(&mut unsatisfied, weights, temperature, rng) {
+            objective += change;
+          }
+        }
+        2 => {
+          if let Some(change) = self.try_reassign(&mut unsatisfied, weights, temperature, rng) {
+            objective += change;
+          }
+        }
+        3 => {
+          self.try_move_student(rng);
+        }
+        _ => {
+          self.try_swap_classes(rng);
+        }
+      }
+
+      if objective < best_objective - OBJECTIVE_TOLERANCE {
+        best_objective = objective;
+        best_class_lists = self.class_lists();
+      }
+    }
+
+    if best_objective < objective - OBJECTIVE_TOLERANCE {
+      self.set_class_lists(best_class_lists);
+    }
+
+    AnnealReport {
+      objective_before,
+      objective_after: self.unmet_request_penalty(student_list, weights),
+      placed_before,
+      placed_after: self.satisfied_request_count(student_list),
+      iteration_count,
+    }
+  }
+
+  fn class_lists(&self) -> Vec<Vec<Class>> {
+    self
+      .slot_list
+      .iter()
+      .map(|slot| slot.lock().unwrap().class_list.clone())
+      .collect()
+  }
+
+  fn set_class_lists(&mut self, class_lists: Vec<Vec<Class>>) {
+    for (slot, class_list) in self.slot_list.iter().zip(class_lists) {
+      slot.lock().unwrap().class_list = class_list;
+    }
+  }
+
+  // Seats an unsatisfied request in a class with space in one of the student's free slots.
+  // Returns the change in the objective when it does.
+  fn try_insert(
+    &mut self,
+    unsatisfied: &mut Vec<(Weak<Student>, Weak<Subject>)>,
+    weights: &ObjectiveWeights,
+    rng: &mut impl Rng,
+  ) -> Option<f64> {
+    if unsatisfied.is_empty() {
+      return None;
+    }
+    let index = rng.gen_range(0..unsatisfied.len());
+    let (student, subject) = unsatisfied[index].clone();
+    if self.available_slots(Weak::clone(&student), Weak::clone(&subject)) == 0 {
+      return None;
+    }
+    let change = -weights.unmet_request_weight(&student.upgrade().unwrap(), &subject);
+    self.add_student_to_subject(student, subject).unwrap();
+    unsatisfied.swap_remove(index);
+    Some(change)
+  }
+
+  // Takes a student out of a class that can spare them, giving up one satisfied request
+  fn try_eject(
+    &mut self,
+    unsatisfied: &mut Vec<(Weak<Student>, Weak<Subject>)>,
+    weights: &ObjectiveWeights,
+    temperature: f64,
+    rng: &mut impl Rng,
+  ) -> Option<f64> {
+    let mut slot = self.slot_list.choose(rng).unwrap().lock().unwrap();
+    let class = slot.class_list.choose_mut(rng)?;
+    if class.removed || !class.can_lose_student() {
+      return None;
+    }
+    let student = Weak::clone(class.student_list.choose(rng).unwrap());
+    let change = weights.unmet_request_weight(&student.upgrade().unwrap(), &class.subject);
+    if !accepts(change, temperature, rng) {
+      return None;
+    }
+    class.remove_student(Weak::clone(&student));
+    unsatisfied.push((student, Weak::clone(&class.subject)));
+    Some(change)
+  }
+
+  // Swaps one of a student's classes for an unsatisfied request that has space in that slot
+  fn try_reassign(
+    &mut self,
+    unsatisfied: &mut [(Weak<Student>, Weak<Subject>)],
+    weights: &ObjectiveWeights,
+    temperature: f64,
+    rng: &mut impl Rng,
+  ) -> Option<f64> {
+    if unsatisfied.is_empty() {
+      return None;
+    }
+    let index = rng.gen_range(0..unsatisfied.len());
+    let (student, subject) = unsatisfied[index].clone();
+    let mut slot = self.slot_list.choose(rng).unwrap().lock().unwrap();
+    if !slot.contains_joinable_subject(Weak::clone(&subject))
+      || !slot.can_lose_student(Weak::clone(&student))
+    {
+      return None;
+    }
+    let student_arc = student.upgrade().unwrap();
+    let other_subject = slot.get_student_subject(Weak::clone(&student)).unwrap();
+    let change = weights.unmet_request_weight(&student_arc, &other_subject)
+      - weights.unmet_request_weight(&student_arc, &subject);
+    if !accepts(change, temperature, rng) {
+      return None;
+    }
+    slot.remove_student(Weak::clone(&student)).unwrap();
+    slot
+      .add_student_to_subject(Weak::clone(&student), subject)
+      .unwrap();
+    unsatisfied[index] = (student, other_subject);
+    Some(change)
+  }
+
+  // Moves a student to a parallel class of the same subject in another slot they have free
+  fn try_move_student(&mut self, rng: &mut impl Rng) -> bool {
+    let from = rng.gen_range(0..self.slot_list.len());
+    let to = rng.gen_range(0..self.slot_list.len());
+    if from == to {
+      return false;
+    }
+    let mut from_slot = self.slot_list[from].lock().unwrap();
+    let mut to_slot = self.slot_list[to].lock().unwrap();
+    let Some(class) = from_slot.class_list.choose_mut(rng) else {
+      return false;
+    };
+    if class.removed || !class.can_lose_student() {
+      return false;
+    }
+    let student = Weak::clone(class.student_list.choose(rng).unwrap());
+    if !to_slot.is_free_for(Weak::clone(&student))
+      || !to_slot.contains_joinable_subject(Weak::clone(&class.subject))
+    {
+      return false;
+    }
+    class.remove_student(Weak::clone(&student));
+    to_slot
+      .add_student_to_subject(student, Weak::clone(&class.subject))
+      .unwrap();
+    true
+  }
+
+  // Moves a class to another slot, exchanging it with one of that slot's classes half of the time
+  fn try_swap_classes(&mut self, rng: &mut impl Rng) -> bool {
+    let from = rng.gen_range(0..self.slot_list.len());
+    let to = rng.gen_range(0..self.slot_list.len());
+    if from == to {
+      return false;
+    }
+    let mut from_slot = self.slot_list[from].lock().unwrap();
+    let mut to_slot = self.slot_list[to].lock().unwrap();
+    let Some(class_index) = live_class_index(&from_slot.class_list, rng) else {
+      return false;
+    };
+    let other_index = if rng.gen_bool(0.5) {
+      live_class_index(&to_slot.class_list, rng)
+    } else {
+      None
+    };
+
+    // Every student must be free in, and taught in, the slot their class moves to
+    let class = &from_slot.class_list[class_index];
+    for student in &class.student_list {
+      if !to_slot.is_open_to(student)
+        || (to_slot.get_student_class_index(Weak::clone(student)) != other_index
+          && to_slot.contains_student(Weak::clone(student)))
+      {
+        return false;
+      }
+    }
+    if let Some(other_index) = other_index {
+      for student in &to_slot.class_list[other_index].student_list {
+        if !from_slot.is_open_to(student)
+          || (from_slot.get_student_class_index(Weak::clone(student)) != Some(class_index)
+            && from_slot.contains_student(Weak::clone(student)))
+        {
+          return false;
+        }
+      }
+    }
+
+    // Both departments must have a classroom left in the slot their class moves to
+    let department = Weak::clone(&class.department);
+    let mut to_count = to_slot.department_class_count(Weak::clone(&department)) + 1;
+    if let Some(other_index) = other_index {
+      let other_department = Weak::clone(&to_slot.class_list[other_index].department);
+      if other_department.ptr_eq(&department) {
+        to_count -= 1;
+      } else {
+        let from_count = from_slot.department_class_count(Weak::clone(&other_department)) + 1;
+        if from_count > from_slot.department_room_count(other_department) {
+          return false;
+        }
+      }
+    }
+    if to_count > to_slot.department_room_count(department) {
+      return false;
+    }
+    // ...and a room there that holds their class
+    let class = &from_slot.class_list[class_index];
+    let arriving = (&class.department, &class.subject, class.student_list.len());
+    if !to_slot.rooms_hold_after(other_index, None, Some(arriving)) {
+      return false;
+    }
+    if let Some(other_index) = other_index {
+      let other = &to_slot.class_list[other_index];
+      let arriving = (&other.department, &other.subject, other.student_list.len());
+      if !from_slot.rooms_hold_after(Some(class_index), None, Some(arriving)) {
+        return false;
+      }
+    }
+
+    let class = from_slot.class_list.remove(class_index);
+    if let Some(other_index) = other_index {
+      let other = to_slot.class_list.remove(other_index);
+      from_slot.class_list.push(other);
+    }
+    to_slot.class_list.push(class);
+    true
+  }
+}
+
+// Improvements are always accepted, and worse moves less often the worse they are and the
+// cooler it gets
+fn accepts(change: f64, temperature: f64, rng: &mut impl Rng) -> bool {
+  change <= 0.0 || rng.gen::<f64>() < (-change / temperature).exp()
+}
+
+fn live_class_index(class_list: &[Class], rng: &mut impl Rng) -> Option<usize> {
+  let live_class_indices = class_list
+    .iter()
+    .enumerate()
+    .filter(|(_, class)| !class.removed)
+    .map(|(index, _)| index)
+    .collect::<Vec<_>>();
+  live_class_indices.choose(rng).copied()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::random;
+  use std::time::Duration;
+
+  use super::*;
+
+  #[test]
+  fn test_anneal_never_worsens_objective() {
+    let mut high_school = random::random_schedule(200, 12, 40, 8);
+    high_school.sort().unwrap();
+    let before = high_school
+      .timetable
+      .unmet_request_penalty(&high_school.student_list, &high_school.objective_weights);
+
+    let mut rng = high_school.rng();
+    let report = high_school.timetable.anneal(
+      &high_school.student_list,
+      &AnnealConfig {
+        time_limit: Duration::from_millis(200),
+        ..Default::default()
+      },
+      &high_school.objective_weights,
+      &mut rng,
+    );
+
+    assert_eq!(report.objective_before, before);
+    assert!(report.objective_after <= report.objective_before);
+    assert_eq!(
+      high_school
+        .timetable
+        .satisfied_request_count(&high_school.student_list),
+      report.placed_after
+    );
+  }
+
+  #[test]
+  fn test_sort_with_anneal_config() {
+    let mut high_school = random::random_schedule_from_seed(42, 200, 12, 40, 8);
+    high_school.anneal_config = Some(AnnealConfig {
+      iteration_limit: Some(2000),
+      ..Default::default()
+    });
+    let report = high_school.sort().unwrap();
+
+    let anneal_report = report.anneal_report.unwrap();
+    assert_eq!(anneal_report.placed_after, report.placed_count());
+    assert!(anneal_report.objective_after <= anneal_report.objective_before);
+    assert!(report.to_string().contains(&anneal_report.to_string()));
+  }
+
+  #[test]
+  fn test_anneal_keeps_classes_within_limits() {
+    let mut high_school = random::random_schedule(200, 12, 40, 8);
+    high_school.sort().unwrap();
+    let mut rng = high_school.rng();
+    high_school.timetable.anneal(
+      &high_school.student_list,
+      &AnnealConfig {
+        time_limit: Duration::from_millis(200),
+        ..Default::default()
+      },
+      &high_school.objective_weights,
+      &mut rng,
+    );
+
+    for slot in &high_school.timetable.slot_list {
+      let slot = slot.lock().unwrap();
+      for class in slot.class_list.iter().filter(|class| !class.removed) {
+        let department = class.department.upgrade().unwrap();
+        assert!(class.student_list.len() >= department.min_class_size);
+        assert!(class.student_list.len() <= department.max_class_size);
+        assert!(
+          slot.department_class_count(Weak::clone(&class.department))
+            <= slot.department_room_count(Weak::clone(&class.department))
+        );
+      }
+    }
+    for student in &high_school.student_list {
+      let mut seen = Vec::new();
+      for slot in &high_school.timetable.slot_list {
+        if let Some(subject) = slot
+          .lock()
+          .unwrap()
+          .get_student_subject(Arc::downgrade(student))
+        {
+          assert!(!seen.iter().any(|x: &Weak<Subject>| x.ptr_eq(&subject)));
+          seen.push(subject);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_anneal_repeatable_with_seed_and_iteration_limit() {
+    let config = AnnealConfig {
+      time_limit: Duration::from_secs(60),
+      iteration_limit: Some(20000),
+      ..Default::default()
+    };
+    let mut report_list = Vec::new();
+    for _ in 0..2 {
+      let mut high_school = random::random_schedule_from_seed(42, 200, 12, 40, 8);
+      high_school.sort().unwrap();
+      let mut rng = high_school.rng();
+      report_list.push(high_school.timetable.anneal(
+        &high_school.student_list,
+        &config,
+        &high_school.objective_weights,
+        &mut rng,
+      ));
+    }
+    assert_eq!(report_list[0], report_list[1]);
+    assert_eq!(report_list[0].iteration_count, 20000);
+  }
+
+  #[test]
+  fn test_anneal_keeps_first_choices() {
+    let mut high_school = crate::Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school.new_department("Art", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Drawing", vec!["Art"]).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Drawing"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Drawing", "Painting"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Three", "3", vec!["Calculus"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Four", "4", vec!["Painting", "Calculus"])
+      .unwrap();
+    let subject = |name: &str| Arc::downgrade(&high_school.get_subject(name).unwrap());
+    let student = |id: &str| Arc::downgrade(&high_school.get_student(id).unwrap());
+    let (maths, art) = (
+      Arc::downgrade(&high_school.get_department("Maths").unwrap()),
+      Arc::downgrade(&high_school.get_department("Art").unwrap()),
+    );
+    // Art runs one class a slot, so someone misses out. Person One could swap Calculus for
+    // Drawing, placing as many requests but giving up their first choice.
+    let class_lists = vec![
+      vec![
+        Class::new(
+          subject("Calculus"),
+          Weak::clone(&maths),
+          vec![student("1"), student("3"), student("4")],
+        ),
+        Class::new(subject("Drawing"), Weak::clone(&art), vec![student("2")]),
+      ],
+      vec![Class::new(
+        subject("Painting"),
+        art,
+        vec![student("2"), student("4")],
+      )],
+    ];
+    high_school.timetable.set_class_lists(class_lists);
+
+    let mut rng = high_school.rng();
+    let report = high_school.timetable.anneal(
+      &high_school.student_list,
+      &AnnealConfig {
+        iteration_limit: Some(2000),
+        time_limit: Duration::from_secs(60),
+        ..Default::default()
+      },
+      &high_school.objective_weights,
+      &mut rng,
+    );
+    // Other students may give up a last choice instead, but never a first one
+    assert_eq!(report.objective_after, report.objective_before);
+    let score = high_school
+      .timetable
+      .score(&high_school.student_list, &high_school.objective_weights);
+    assert_eq!(score.unmet_request_count, 1);
+    assert_eq!(score.unmet_rank, 0);
+  }
+
+  #[test]
+  fn test_anneal_time_limit_before_iteration_limit() {
+    let mut high_school = random::random_schedule_from_seed(42, 200, 12, 40, 8);
+    high_school.sort().unwrap();
+    let mut rng = high_school.rng();
+    let start = Instant::now();
+    let report = high_school.timetable.anneal(
+      &high_school.student_list,
+      &AnnealConfig {
+        time_limit: Duration::from_millis(50),
+        iteration_limit: Some(usize::MAX),
+        ..Default::default()
+      },
+      &high_school.objective_weights,
+      &mut rng,
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(report.iteration_count < usize::MAX);
+  }
+}
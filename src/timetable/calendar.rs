@@ -0,0 +1,444 @@
+//! iCalendar (RFC 5545) export of timetables laid out over the dates of a term
+
+use super::{Class, PeriodMap, Timetable};
+use crate::prelude::*;
+use std::time::SystemTime;
+
+// School days in a calendar week, Monday to Friday
+const SCHOOL_DAYS_PER_WEEK: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CalendarDate {
+  pub year: i32,
+  pub month: u32,
+  pub day: u32,
+}
+
+impl CalendarDate {
+  pub fn new(year: i32, month: u32, day: u32) -> Self {
+    Self { year, month, day }
+  }
+  // Days since 1970-01-01, counted in the proleptic Gregorian calendar
+  pub fn day_number(&self) -> i64 {
+    let year = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = self.month as i64;
+    let day_of_year =
+      (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+  }
+  pub fn from_day_number(day_number: i64) -> Self {
+    let day_number = day_number + 719468;
+    let era = day_number.div_euclid(146097);
+    let day_of_era = day_number - era * 146097;
+    let year_of_era =
+      (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+      month_index + 3
+    } else {
+      month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    Self {
+      year: year as i32,
+      month,
+      day,
+    }
+  }
+  pub fn is_valid(&self) -> bool {
+    (1..=12).contains(&self.month)
+      && self.day >= 1
+      && Self::from_day_number(self.day_number()) == *self
+  }
+  // Day of the week, with Monday as 0
+  pub fn weekday(&self) -> usize {
+    (self.day_number() + 3).rem_euclid(7) as usize
+  }
+  pub fn add_days(&self, days: i64) -> Self {
+    Self::from_day_number(self.day_number() + days)
+  }
+  // The date the given number of school days later, skipping weekends
+  pub fn add_school_days(&self, school_days: usize) -> Self {
+    let mut date = *self;
+    for _ in 0..school_days {
+      date = date.add_days(1);
+      while date.weekday() >= SCHOOL_DAYS_PER_WEEK {
+        date = date.add_days(1);
+      }
+    }
+    date
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockTime {
+  pub hour: u32,
+  pub minute: u32,
+}
+
+impl ClockTime {
+  pub fn new(hour: u32, minute: u32) -> Self {
+    Self { hour, minute }
+  }
+}
+
+// The dates a timetable cycle runs over. The cycle's days fall on consecutive school days,
+// Monday to Friday, and repeat until the end of term.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+  // First day of term, which is the first day of the cycle
+  pub start: CalendarDate,
+  // Last day of term
+  pub end: CalendarDate,
+  // First and last day of each break, such as a mid-term holiday. The cycle carries on
+  // through breaks, but no classes are held on them.
+  pub break_list: Vec<(CalendarDate, CalendarDate)>,
+  // Start and end of each period of the day, breaks included
+  pub period_time_list: Vec<(ClockTime, ClockTime)>,
+}
+
+impl Term {
+  pub fn is_break(&self, date: CalendarDate) -> bool {
+    self
+      .break_list
+      .iter()
+      .any(|(first, last)| *first <= date && date <= *last)
+  }
+}
+
+fn format_date_time(date: CalendarDate, time: ClockTime) -> String {
+  format!(
+    "{:04}{:02}{:02}T{:02}{:02}00",
+    date.year, date.month, date.day, time.hour, time.minute
+  )
+}
+
+// Escapes the characters TEXT values give meaning to
+fn escape_text(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace(';', "\\;")
+    .replace(',', "\\,")
+    .replace('\n', "\\n")
+}
+
+// Ends the line with CRLF, folding it so that no line is longer than 75 octets
+fn push_line(output: &mut String, line: &str) {
+  let mut length = 0;
+  for character in line.chars() {
+    if length + character.len_utf8() > 75 {
+      output.push_str("\r\n ");
+      length = 1;
+    }
+    output.push(character);
+    length += character.len_utf8();
+  }
+  output.push_str("\r\n");
+}
+
+impl PeriodMap {
+  // Calendar of the classes that match the filter, each meeting in every period its slot is
+  // mapped to, recurring through the term with the term's breaks left out
+  pub fn calendar(
+    &self,
+    timetable: &Timetable,
+    term: &Term,
+    name: &str,
+    // Given each class's slot, its index in the slot's class list and the class itself
+    filter: impl Fn(usize, usize, &Class) -> bool,
+  ) -> Result<String> {
+    let day_count = self.grid.day_list.len();
+    if day_count == 0 || !day_count.is_multiple_of(SCHOOL_DAYS_PER_WEEK) {
+      return Err(Error::Generic(format!(
+        "A cycle of {} days does not repeat on the same weekdays",
+        day_count
+      )));
+    }
+    if term.period_time_list.len() != self.grid.period_count {
+      return Err(Error::Generic(format!(
+        "Term has times for {} periods but the grid has {} periods a day",
+        term.period_time_list.len(),
+        self.grid.period_count
+      )));
+    }
+    if !term.start.is_valid() || !term.end.is_valid() || term.end < term.start {
+      return Err(Error::Generic(format!(
+        "Term from {:?} to {:?} is not a valid range of dates",
+        term.start, term.end
+      )));
+    }
+    if term.start.weekday() >= SCHOOL_DAYS_PER_WEEK {
+      return Err(Error::Generic(format!(
+        "Term cannot start on a weekend ({:?})",
+        term.start
+      )));
+    }
+    let week_interval = day_count / SCHOOL_DAYS_PER_WEEK;
+    let until = format_date_time(term.end, ClockTime::new(23, 59));
+    let stamp = SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .map_or(0, |x| x.as_secs() as i64);
+    let stamp = format!(
+      "{}Z",
+      format_date_time(
+        CalendarDate::from_day_number(stamp.div_euclid(86400)),
+        ClockTime::new(
+          (stamp.rem_euclid(86400) / 3600) as u32,
+          (stamp.rem_euclid(3600) / 60) as u32
+        )
+      )
+    );
+
+    // Events are told apart by the calendar's name and where they are in the timetable
+    let uid_name: String = name.chars().filter(|x| x.is_ascii_alphanumeric()).collect();
+
+    let mut output = String::new();
+    push_line(&mut output, "BEGIN:VCALENDAR");
+    push_line(&mut output, "VERSION:2.0");
+    push_line(&mut output, "PRODID:-//schedule//timetable//EN");
+    push_line(&mut output, &format!("X-WR-CALNAME:{}", escape_text(name)));
+    for (slot_index, slot) in timetable.slot_list.iter().enumerate() {
+      let Some(period_list) = self.slot_period_list.get(slot_index) else {
+        continue;
+      };
+      let slot = slot.lock().unwrap();
+      for (class_index, class) in slot.class_list.iter().enumerate() {
+        if class.removed || !filter(slot_index, class_index, class) {
+          continue;
+        }
+        let subject = class.subject.upgrade().unwrap();
+        let department = class.department.upgrade().unwrap();
+        for period in period_list {
+          let first = term.start.add_school_days(period.day);
+          if first > term.end {
+            continue;
+          }
+          let (start_time, end_time) = term.period_time_list[period.period];
+          push_line(&mut output, "BEGIN:VEVENT");
+          push_line(
+            &mut output,
+            &format!(
+              "UID:{}-slot{}-class{}-day{}-period{}@schedule",
+              uid_name, slot_index, class_index, period.day, period.period
+            ),
+          );
+          push_line(&mut output, &format!("DTSTAMP:{}", stamp));
+          push_line(
+            &mut output,
+            &format!("DTSTART:{}", format_date_time(first, start_time)),
+          );
+          push_line(
+            &mut output,
+            &format!("DTEND:{}", format_date_time(first, end_time)),
+          );
+          push_line(
+            &mut output,
+            &format!(
+              "RRULE:FREQ=WEEKLY;INTERVAL={};UNTIL={}",
+              week_interval, until
+            ),
+          );
+          // Every occurrence that lands in a break
+          let mut date = first;
+          while date <= term.end {
+            if term.is_break(date) {
+              push_line(
+                &mut output,
+                &format!("EXDATE:{}", format_date_time(date, start_time)),
+              );
+            }
+            date = date.add_days(7 * week_interval as i64);
+          }
+          push_line(
+            &mut output,
+            &format!("SUMMARY:{}", escape_text(&subject.name)),
+          );
+          push_line(
+            &mut output,
+            &format!("DESCRIPTION:{}", escape_text(&department.name)),
+          );
+          if let Some(room) = class.room.as_ref().and_then(|x| x.upgrade()) {
+            push_line(
+              &mut output,
+              &format!("LOCATION:{}", escape_text(&room.name)),
+            );
+          }
+          push_line(&mut output, "END:VEVENT");
+        }
+      }
+    }
+    push_line(&mut output, "END:VCALENDAR");
+    Ok(output)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{PeriodGrid, Schedule};
+  use std::sync::{Arc, Weak};
+
+  #[test]
+  fn test_calendar_dates() {
+    let date = CalendarDate::new(2024, 2, 28);
+    assert_eq!(date.add_days(1), CalendarDate::new(2024, 2, 29));
+    assert_eq!(date.add_days(2), CalendarDate::new(2024, 3, 1));
+    assert_eq!(CalendarDate::new(1970, 1, 1).day_number(), 0);
+    assert_eq!(
+      CalendarDate::new(2023, 12, 31).add_days(1),
+      CalendarDate::new(2024, 1, 1)
+    );
+    // 2024-02-29 was a Thursday
+    assert_eq!(CalendarDate::new(2024, 2, 29).weekday(), 3);
+    assert_eq!(
+      CalendarDate::new(2024, 3, 1).add_school_days(1),
+      CalendarDate::new(2024, 3, 4)
+    );
+    assert!(!CalendarDate::new(2023, 2, 29).is_valid());
+  }
+
+  #[test]
+  fn test_folding() {
+    let mut output = String::new();
+    push_line(&mut output, &format!("SUMMARY:{}", "x".repeat(100)));
+    let line_list: Vec<&str> = output.trim_end().split("\r\n").collect();
+    assert_eq!(line_list.len(), 2);
+    assert_eq!(line_list[0].len(), 75);
+    assert!(line_list[1].starts_with(' '));
+  }
+
+  #[test]
+  fn test_student_calendar() {
+    let mut high_school = Schedule::with_slot_count(5);
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus"])
+      .unwrap();
+    high_school.sort().unwrap();
+    high_school
+      .set_period_grid(
+        PeriodGrid::new(vec!["Mon", "Tue", "Wed", "Thu", "Fri"], 2),
+        1,
+      )
+      .unwrap();
+    let term = Term {
+      // A Monday
+      start: CalendarDate::new(2026, 2, 2),
+      end: CalendarDate::new(2026, 2, 27),
+      break_list: vec![(
+        CalendarDate::new(2026, 2, 16),
+        CalendarDate::new(2026, 2, 20),
+      )],
+      period_time_list: vec![
+        (ClockTime::new(9, 0), ClockTime::new(10, 0)),
+        (ClockTime::new(10, 0), ClockTime::new(11, 0)),
+      ],
+    };
+
+    let slot = (0..5)
+      .find(|&x| {
+        !high_school.timetable.slot_list[x]
+          .lock()
+          .unwrap()
+          .class_list
+          .is_empty()
+      })
+      .unwrap();
+    let period = high_school.period_map.as_ref().unwrap().slot_period_list[slot][0];
+    let first = term.start.add_school_days(period.day);
+    let start_time = term.period_time_list[period.period].0;
+    let calendar = high_school.student_calendar("1", &term).unwrap();
+    let line_list: Vec<&str> = calendar.split("\r\n").collect();
+    assert_eq!(line_list[0], "BEGIN:VCALENDAR");
+    assert_eq!(
+      line_list.iter().filter(|x| **x == "BEGIN:VEVENT").count(),
+      1
+    );
+    assert!(
+      line_list.contains(&format!("DTSTART:{}", format_date_time(first, start_time)).as_str())
+    );
+    assert!(line_list.contains(&"RRULE:FREQ=WEEKLY;INTERVAL=1;UNTIL=20260227T235900"));
+    // The class meets once in the week of the break
+    let exdate = format!(
+      "EXDATE:{}",
+      format_date_time(first.add_days(14), start_time)
+    );
+    assert_eq!(
+      line_list
+        .iter()
+        .filter(|x| x.starts_with("EXDATE"))
+        .collect::<Vec<_>>(),
+      vec![&exdate.as_str()]
+    );
+    assert!(line_list.contains(&"SUMMARY:Calculus"));
+    assert!(line_list.contains(&"DESCRIPTION:Maths"));
+
+    assert!(high_school.student_calendar("2", &term).is_err());
+  }
+
+  #[test]
+  fn test_class_calendar() {
+    let mut high_school = Schedule::with_slot_count(5);
+    high_school.new_department("Maths", 1, 30, 2).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject_with_slot_demand("Statistics", vec!["Maths"], 2)
+      .unwrap();
+    high_school
+      .set_period_grid(
+        PeriodGrid::new(vec!["Mon", "Tue", "Wed", "Thu", "Fri"], 2),
+        1,
+      )
+      .unwrap();
+    let calculus = Arc::downgrade(&high_school.get_subject("Calculus").unwrap());
+    let statistics = Arc::downgrade(&high_school.get_subject("Statistics").unwrap());
+    let maths = Arc::downgrade(&high_school.get_department("Maths").unwrap());
+    // Two empty Calculus classes in one slot, and a Statistics group over two slots
+    for _ in 0..2 {
+      high_school.timetable.slot_list[0]
+        .lock()
+        .unwrap()
+        .class_list
+        .push(Class::new(
+          Weak::clone(&calculus),
+          Weak::clone(&maths),
+          Vec::new(),
+        ));
+    }
+    for slot in 1..3 {
+      high_school.timetable.slot_list[slot]
+        .lock()
+        .unwrap()
+        .class_list
+        .push(Class {
+          group: Some(0),
+          ..Class::new(Weak::clone(&statistics), Weak::clone(&maths), Vec::new())
+        });
+    }
+    let term = Term {
+      start: CalendarDate::new(2026, 2, 2),
+      end: CalendarDate::new(2026, 2, 27),
+      break_list: Vec::new(),
+      period_time_list: vec![
+        (ClockTime::new(9, 0), ClockTime::new(10, 0)),
+        (ClockTime::new(10, 0), ClockTime::new(11, 0)),
+      ],
+    };
+    let event_count = |calendar: String| calendar.matches("BEGIN:VEVENT").count();
+    assert_eq!(
+      event_count(high_school.class_calendar(0, 1, &term).unwrap()),
+      1
+    );
+    assert_eq!(
+      event_count(high_school.class_calendar(2, 0, &term).unwrap()),
+      2
+    );
+    assert!(high_school.class_calendar(0, 2, &term).is_err());
+  }
+}
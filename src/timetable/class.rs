@@ -1,4 +1,4 @@
-use crate::{Department, Student, Subject};
+use crate::{Department, Room, Student, Subject, Teacher};
 use std::sync::Weak;
 
 #[derive(Debug, Clone)]
@@ -7,9 +7,28 @@ pub struct Class {
   pub department: Weak<Department>,
   pub student_list: Vec<Weak<Student>>,
   pub removed: bool,
+  pub teacher: Option<Weak<Teacher>>,
+  pub room: Option<Weak<Room>>,
+  // Classes of a multi-slot subject that share the same students share a group
+  pub group: Option<usize>,
 }
 
 impl Class {
+  pub fn new(
+    subject: Weak<Subject>,
+    department: Weak<Department>,
+    student_list: Vec<Weak<Student>>,
+  ) -> Self {
+    Self {
+      subject,
+      department,
+      student_list,
+      removed: false,
+      teacher: None,
+      room: None,
+      group: None,
+    }
+  }
   pub fn contains(&self, student: Weak<Student>) -> bool {
     for other in &self.student_list {
       if other.ptr_eq(&student) {
@@ -18,14 +37,33 @@ impl Class {
     }
     false
   }
-  pub fn remove_if_too_small(&mut self) -> usize {
+  pub fn remove_student(&mut self, student: Weak<Student>) -> bool {
+    match self.student_list.iter().position(|x| x.ptr_eq(&student)) {
+      Some(index) => {
+        self.student_list.remove(index);
+        true
+      }
+      None => false,
+    }
+  }
+  pub fn is_full(&self) -> bool {
+    self.student_list.len() >= self.department.upgrade().unwrap().max_class_size
+  }
+  // Whether a student can leave without the class dropping below its minimum size. Students
+  // can only leave a grouped class together with the rest of its group.
+  pub fn can_lose_student(&self) -> bool {
+    self.group.is_none()
+      && self.student_list.len() > self.department.upgrade().unwrap().min_class_size
+  }
+  // Marks the class as removed and returns the students that were in it
+  pub fn remove_if_too_small(&mut self) -> Vec<Weak<Student>> {
     let &min_size = &self.department.upgrade().unwrap().min_class_size;
     let &curr_size = &self.student_list.len();
-    if curr_size < min_size {
+    if !self.removed && curr_size < min_size {
       self.removed = true;
-      curr_size
+      self.student_list.clone()
     } else {
-      0
+      Vec::new()
     }
   }
 }
@@ -68,12 +106,11 @@ mod tests {
         .build()
         .unwrap(),
     );
-    let class = Class {
-      subject: Arc::downgrade(&subject),
-      department: Arc::downgrade(&department),
-      student_list: vec![Arc::downgrade(&student)],
-      removed: false,
-    };
+    let class = Class::new(
+      Arc::downgrade(&subject),
+      Arc::downgrade(&department),
+      vec![Arc::downgrade(&student)],
+    );
     assert!(class.contains(Arc::downgrade(&student)));
     assert!(!class.contains(Arc::downgrade(&student_two)));
   }
@@ -0,0 +1,325 @@
+//! Placing every student in their compulsory subjects, at the cost of optional ones if need be
+
+use super::{Slot, Timetable};
+use crate::{Class, Student, Subject};
+use std::sync::{Arc, Weak};
+
+impl Timetable {
+  // Compulsory subjects the student has no class for
+  pub fn unmet_compulsory(&self, student: &Arc<Student>) -> Vec<Weak<Subject>> {
+    self
+      .unsatisfied_requests(student)
+      .into_iter()
+      .filter(|subject| student.is_compulsory(subject))
+      .collect()
+  }
+
+  // Places every student in their compulsory subjects that the solver left out, opening new
+  // classes only if allowed to. Returns the students and subjects that could still not be
+  // placed.
+  pub fn place_compulsory(
+    &mut self,
+    student_list: &[Arc<Student>],
+    may_open_classes: bool,
+  ) -> Vec<(Weak<Student>, Weak<Subject>)> {
+    let mut pending_list: Vec<(&Arc<Student>, Weak<Subject>)> = Vec::new();
+    for student in student_list {
+      for subject in self.unmet_compulsory(student) {
+        pending_list.push((student, subject));
+      }
+    }
+    let mut unmet_list = Vec::new();
+    for (index, (student, subject)) in pending_list.iter().enumerate() {
+      // Students from here on that still need the subject, who could fill a class opened now
+      let pending_count = pending_list[index..]
+        .iter()
+        .filter(|(_, other)| other.ptr_eq(subject))
+        .count();
+      if !self.place_compulsory_subject(
+        student,
+        Weak::clone(subject),
+        may_open_classes,
+        pending_count,
+      ) {
+        unmet_list.push((Arc::downgrade(student), Weak::clone(subject)));
+      }
+    }
+
+    // Classes opened for too few students, or left too small by students forced out of them,
+    // cannot run
+    let displaced_list = self.remove_small_classes();
+    for (student, subject) in self.place_displaced_students(displaced_list) {
+      let is_unmet = unmet_list
+        .iter()
+        .any(|(x, y)| x.ptr_eq(&student) && y.ptr_eq(&subject));
+      if student.upgrade().unwrap().is_compulsory(&subject) && !is_unmet {
+        unmet_list.push((student, subject));
+      }
+    }
+    unmet_list
+  }
+
+  fn place_compulsory_subject(
+    &mut self,
+    student: &Arc<Student>,
+    subject: Weak<Subject>,
+    may_open_classes: bool,
+    pending_count: usize,
+  ) -> bool {
+    let weak_student = Arc::downgrade(student);
+    if self
+      .place_displaced_student(Weak::clone(&weak_student), Weak::clone(&subject))
+      .is_ok()
+    {
+      return true;
+    }
+    let is_multi_slot = subject.upgrade().unwrap().slot_demand > 1;
+    if may_open_classes {
+      let opened = if is_multi_slot {
+        self
+          .create_group_for_student(Weak::clone(&weak_student), Weak::clone(&subject))
+          .is_ok()
+          && self.keep_new_group(&weak_student, &subject, pending_count)
+      } else {
+        self.slot_list.iter().any(|slot| {
+          let mut slot = slot.lock().unwrap();
+          slot.is_free_for(Weak::clone(&weak_student))
+            && open_class(&mut slot, &weak_student, &subject, pending_count)
+        })
+      };
+      if opened {
+        return true;
+      }
+    }
+    // Groups would need the student out of several slots at once, so are not forced in
+    if is_multi_slot {
+      return false;
+    }
+
+    // Take the student out of an optional class in a slot where the subject can go instead
+    for slot in &self.slot_list {
+      let mut slot = slot.lock().unwrap();
+      let Some(class_index) = slot.get_student_class_index(Weak::clone(&weak_student)) else {
+        continue;
+      };
+      let class = &slot.class_list[class_index];
+      if class.group.is_some() || student.is_compulsory(&class.subject) {
+        continue;
+      }
+      let optional = Weak::clone(&class.subject);
+      slot.class_list[class_index].remove_student(Weak::clone(&weak_student));
+      let placed = slot
+        .add_student_to_subject(Weak::clone(&weak_student), Weak::clone(&subject))
+        .is_ok()
+        || (may_open_classes && open_class(&mut slot, &weak_student, &subject, pending_count));
+      if !placed {
+        slot.class_list[class_index]
+          .student_list
+          .push(Weak::clone(&weak_student));
+        continue;
+      }
+      if slot.class_list[class_index].student_list.is_empty() {
+        slot.class_list[class_index].removed = true;
+      }
+      drop(slot);
+      // The optional subject may still fit in another slot
+      let _ = self.place_displaced_student(weak_student, optional);
+      return true;
+    }
+    false
+  }
+
+  // Closes the group just opened for the student if too few students could join it
+  fn keep_new_group(
+    &mut self,
+    student: &Weak<Student>,
+    subject: &Weak<Subject>,
+    pending_count: usize,
+  ) -> bool {
+    let Some((group, is_large_enough)) = self.slot_list.iter().find_map(|slot| {
+      slot
+        .lock()
+        .unwrap()
+        .class_list
+        .iter()
+        .find(|x| {
+          x.group.is_some() && x.subject.ptr_eq(subject) && x.contains(Weak::clone(student))
+        })
+        .map(|x| (x.group, can_reach_minimum(x, pending_count)))
+    }) else {
+      return false;
+    };
+    if !is_large_enough {
+      for slot in &self.slot_list {
+        slot.lock().unwrap().class_list.retain(|x| x.group != group);
+      }
+    }
+    is_large_enough
+  }
+}
+
+fn can_reach_minimum(class: &Class, pending_count: usize) -> bool {
+  pending_count >= class.department.upgrade().unwrap().min_class_size
+}
+
+// Opens a class of the subject for the student, as long as the students still needing the
+// subject could bring it up to its department's minimum size
+fn open_class(
+  slot: &mut Slot,
+  student: &Weak<Student>,
+  subject: &Weak<Subject>,
+  pending_count: usize,
+) -> bool {
+  if slot
+    .create_class_for_student(Weak::clone(student), Weak::clone(subject))
+    .is_err()
+  {
+    return false;
+  }
+  if can_reach_minimum(slot.class_list.last().unwrap(), pending_count) {
+    return true;
+  }
+  slot.class_list.pop();
+  false
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{prelude::*, Class, Schedule};
+
+  #[test]
+  fn test_compulsory_forces_optional_out() {
+    let mut high_school = Schedule::with_slot_count(1);
+    high_school.new_department("English", 1, 30, 1).unwrap();
+    high_school.new_department("Art", 1, 30, 1).unwrap();
+    high_school
+      .new_compulsory_subject("English", vec!["English"])
+      .unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "1", "1", vec!["English"])
+      .unwrap();
+    high_school
+      .new_student("Person", "2", "2", vec!["English"])
+      .unwrap();
+    let english = Arc::downgrade(&high_school.get_subject("English").unwrap());
+    let painting = Arc::downgrade(&high_school.get_subject("Painting").unwrap());
+    let one = Arc::downgrade(&high_school.student_list[0]);
+    let two = Arc::downgrade(&high_school.student_list[1]);
+
+    // The second student has been put in painting in the only slot English runs in
+    let mut timetable = Timetable::new(1);
+    timetable.slot_list[0].lock().unwrap().class_list = vec![
+      Class::new(
+        english,
+        Arc::downgrade(&high_school.get_department("English").unwrap()),
+        vec![one],
+      ),
+      Class::new(
+        painting,
+        Arc::downgrade(&high_school.get_department("Art").unwrap()),
+        vec![Weak::clone(&two)],
+      ),
+    ];
+    assert!(timetable
+      .place_compulsory(&high_school.student_list, true)
+      .is_empty());
+
+    let slot = timetable.slot_list[0].lock().unwrap();
+    assert_eq!(slot.class_list[0].student_list.len(), 2);
+    assert!(slot.class_list[1].removed);
+  }
+
+  #[test]
+  fn test_forced_out_class_too_small() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("English", 1, 30, 1).unwrap();
+    high_school.new_department("Art", 2, 30, 1).unwrap();
+    high_school
+      .new_compulsory_subject("English", vec!["English"])
+      .unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "1", "1", vec!["English", "Painting"])
+      .unwrap();
+    high_school
+      .new_student("Person", "2", "2", vec!["Painting"])
+      .unwrap();
+    let english = Arc::downgrade(&high_school.get_subject("English").unwrap());
+    let one = Arc::downgrade(&high_school.student_list[0]);
+    let two = Arc::downgrade(&high_school.student_list[1]);
+
+    // English only fits in the one slot the first student has painting in, and forcing them out
+    // of it leaves painting below Art's minimum of two
+    let mut timetable = Timetable::new(1);
+    timetable.slot_list[0].lock().unwrap().class_list = vec![Class::new(
+      Arc::downgrade(&high_school.get_subject("Painting").unwrap()),
+      Arc::downgrade(&high_school.get_department("Art").unwrap()),
+      vec![Weak::clone(&one), Weak::clone(&two)],
+    )];
+    assert!(timetable
+      .place_compulsory(&high_school.student_list, true)
+      .is_empty());
+
+    let slot = timetable.slot_list[0].lock().unwrap();
+    assert!(slot.class_list[0].removed);
+    assert!(slot.class_list[1].subject.ptr_eq(&english));
+    assert!(!slot.contains_student(two));
+  }
+
+  #[test]
+  fn test_compulsory_class_below_minimum() {
+    let mut high_school = Schedule::with_slot_count(1);
+    high_school.new_department("English", 2, 30, 1).unwrap();
+    high_school
+      .new_compulsory_subject("English", vec!["English"])
+      .unwrap();
+    high_school
+      .new_student("Person", "1", "1", vec!["English"])
+      .unwrap();
+
+    // A class of one would be below English's minimum of two
+    let mut timetable = Timetable::new(1);
+    assert_eq!(
+      timetable
+        .place_compulsory(&high_school.student_list, true)
+        .len(),
+      1
+    );
+    assert!(timetable.slot_list[0].lock().unwrap().class_list.is_empty());
+
+    // With a second student needing English, the class can run
+    high_school
+      .new_student("Person", "2", "2", vec!["English"])
+      .unwrap();
+    let mut timetable = Timetable::new(1);
+    assert!(timetable
+      .place_compulsory(&high_school.student_list, true)
+      .is_empty());
+    let slot = timetable.slot_list[0].lock().unwrap();
+    assert_eq!(slot.class_list.len(), 1);
+    assert_eq!(slot.class_list[0].student_list.len(), 2);
+  }
+
+  #[test]
+  fn test_compulsory_unmet() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 1, 1, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    for id in ["1", "2", "3"] {
+      high_school
+        .new_student_with_compulsory("Person", id, id, vec!["Calculus"], vec!["Calculus"])
+        .unwrap();
+    }
+
+    // Two classes of one cannot hold three students
+    match high_school.sort() {
+      Err(Error::CompulsoryUnmet(unmet_list)) => {
+        assert_eq!(unmet_list.len(), 1);
+        assert_eq!(unmet_list[0].1, vec!["Calculus".to_string()]);
+      }
+      _ => panic!("Expected compulsory subjects to be unmet"),
+    }
+  }
+}
@@ -1,10 +1,23 @@
+mod anneal;
+mod calendar;
 mod class;
+mod compulsory;
+mod multi_slot;
+mod objective;
+mod period;
+mod reserve;
+mod room_assignment;
 mod slot;
+mod teacher_assignment;
 
 use std::sync::{Arc, Mutex, Weak};
 
-use crate::{prelude::*, Student, Subject};
+use crate::{prelude::*, Cohort, Room, Student, Subject};
+pub use anneal::{AnnealConfig, AnnealReport};
+pub use calendar::{CalendarDate, ClockTime, Term};
 pub use class::Class;
+pub use objective::{ObjectiveWeights, Score};
+pub use period::{Period, PeriodGrid, PeriodMap, WeekGrid};
 pub use slot::Slot;
 
 #[derive(Default, Clone)]
@@ -24,24 +37,96 @@ impl Timetable {
     });
     new_timetable
   }
-  pub fn clear(&mut self) {
-    *self = Self::new(self.slot_list.capacity());
+  // Timetable whose slots can each use every room in the list
+  pub fn with_rooms(slot_count: usize, room_list: &[Arc<Room>]) -> Self {
+    let new_timetable = Self::new(slot_count);
+    for slot in &new_timetable.slot_list {
+      slot.lock().unwrap().room_list = room_list.iter().map(Arc::downgrade).collect();
+    }
+    new_timetable
   }
-  // TODO! Returns the number of subjects that a student could not join
-  pub fn add_student_to_timetable(&mut self, student: Weak<Student>) -> Result<usize> {
-    let mut student_subjects = student.upgrade().unwrap().subject_list.clone();
-    loop {
-      let Ok(subject_added) =
-      self.add_student_to_least_available(Weak::clone(&student), &student_subjects) else {
-        return Ok(student_subjects.len())
+  // Opens each slot to the cohorts taught in it
+  pub fn set_cohorts(&self, cohort_list: &[Arc<Cohort>]) {
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      slot.lock().unwrap().cohort_list = cohort_list
+        .iter()
+        .filter(|cohort| cohort.uses_slot(slot_index))
+        .map(Arc::downgrade)
+        .collect();
+    }
+  }
+  pub fn clear(&mut self) {
+    for slot in &mut self.slot_list {
+      let (room_list, cohort_list) = {
+        let slot = slot.lock().unwrap();
+        (slot.room_list.clone(), slot.cohort_list.clone())
       };
-      student_subjects.remove(
-        student_subjects
-          .iter()
-          .position(|x| subject_added.ptr_eq(x))
-          .unwrap(),
+      *slot = Arc::new(Mutex::new(Slot {
+        room_list,
+        cohort_list,
+        ..Slot::new()
+      }));
+    }
+  }
+  // Greedily adds every student, then removes the classes that are too small and re-places
+  // the students they displaced
+  pub fn sort_students(&mut self, student_list: &[Arc<Student>]) -> Result<()> {
+    for student in student_list {
+      self.add_student_to_timetable(Arc::downgrade(student))?;
+    }
+    let mut displaced_students = self.remove_small_classes();
+    // Compulsory subjects, then higher-ranked choices, get the first pick of the seats left
+    displaced_students.sort_by_key(|(student, subject)| {
+      let student = student.upgrade().unwrap();
+      (!student.is_compulsory(subject), student.rank(subject))
+    });
+    self.place_displaced_students(displaced_students);
+    Ok(())
+  }
+
+  pub fn remove_small_classes(&mut self) -> Vec<(Weak<Student>, Weak<Subject>)> {
+    let mut displaced_students = Vec::new();
+    for slot in &self.slot_list {
+      displaced_students.append(
+        &mut slot
+          .lock()
+          .unwrap()
+          .remove_small_classes_and_get_displaced_students(),
       );
     }
+    displaced_students
+  }
+
+  // Returns the number of subjects that a student could not join
+  pub fn add_student_to_timetable(&mut self, student: Weak<Student>) -> Result<usize> {
+    // Compulsory subjects are placed before optional ones get a chance at the slots
+    let (compulsory_subjects, optional_subjects): (Vec<_>, Vec<_>) = student
+      .upgrade()
+      .unwrap()
+      .subject_list
+      .iter()
+      .cloned()
+      .partition(|x| student.upgrade().unwrap().is_compulsory(x));
+    let mut unjoined_count = 0;
+    for mut student_subjects in [compulsory_subjects, optional_subjects] {
+      while !student_subjects.is_empty() {
+        let Ok(subject_added) =
+          self.add_student_to_least_available(Weak::clone(&student), &student_subjects)
+        else {
+          // The subject tried when none were available has no room, but the rest may still fit
+          student_subjects.remove(0);
+          unjoined_count += 1;
+          continue;
+        };
+        student_subjects.remove(
+          student_subjects
+            .iter()
+            .position(|x| subject_added.ptr_eq(x))
+            .unwrap(),
+        );
+      }
+    }
+    Ok(unjoined_count)
   }
 
   pub fn add_student_to_least_available(
@@ -49,23 +134,24 @@ impl Timetable {
     student: Weak<Student>,
     subject_list: &Vec<Weak<Subject>>,
   ) -> Result<Weak<Subject>> {
-    if subject_list.len() == 0 {
+    if subject_list.is_empty() {
       return Err(Error::Generic(
         "Student has no subjects to add them to".to_string(),
       ));
     }
 
     // Check that some subjects are available
-    let Some(available_subject) = self.get_available_subject(Weak::clone(&student), &subject_list) else {
+    let Some(available_subject) = self.get_available_subject(Weak::clone(&student), subject_list) else {
       // If none are available add an arbitrary one
-      match self.add_student_to_subject(student, Weak::clone(&subject_list.get(0).unwrap())) {
+      match self.add_student_to_subject(student, Weak::clone(subject_list.first().unwrap())) {
         Ok(..) => {},
         Err(k) => return Err(k)
       }
-      return Ok(Weak::clone(&subject_list.get(0).unwrap()));
+      return Ok(Weak::clone(subject_list.first().unwrap()));
     };
 
-    // Find the least available subject that is still available
+    // Find the least available subject that is still available, with ties going to the
+    // student's higher-ranked choice
     let (mut least_available_subject, mut least_nonzero_available_slots) = (
       Weak::clone(&available_subject),
       self.available_slots(Weak::clone(&student), Weak::clone(&available_subject)),
@@ -73,12 +159,12 @@ impl Timetable {
 
     for subject in subject_list {
       let available_slot_number =
-        self.available_slots(Weak::clone(&student), Weak::clone(&subject));
+        self.available_slots(Weak::clone(&student), Weak::clone(subject));
       if available_slot_number == 0 {
         continue;
       } else if available_slot_number < least_nonzero_available_slots {
         (least_available_subject, least_nonzero_available_slots) =
-          (Weak::clone(&subject), available_slot_number);
+          (Weak::clone(subject), available_slot_number);
       }
     }
 
@@ -96,7 +182,7 @@ impl Timetable {
     subject_list: &Vec<Weak<Subject>>,
   ) -> Option<Weak<Subject>> {
     for subject in subject_list {
-      if self.available_slots(Weak::clone(&student), Weak::clone(&subject)) != 0 {
+      if self.available_slots(Weak::clone(&student), Weak::clone(subject)) != 0 {
         return Some(Weak::clone(subject));
       }
     }
@@ -108,6 +194,15 @@ impl Timetable {
     student: Weak<Student>,
     subject: Weak<Subject>,
   ) -> Result<()> {
+    if subject.upgrade().unwrap().slot_demand > 1 {
+      return match self
+        .joinable_group_list(Weak::clone(&student), Weak::clone(&subject))
+        .first()
+      {
+        Some(&group) => self.join_group(student, group),
+        None => self.create_group_for_student(student, subject),
+      };
+    }
     for slot in self.get_student_free_slots(Weak::clone(&student)) {
       if slot
         .upgrade()
@@ -132,7 +227,7 @@ impl Timetable {
     student: Weak<Student>,
     subject: Weak<Subject>,
   ) -> Result<()> {
-    match self.get_student_free_slots(Weak::clone(&student)).get(0) {
+    match self.get_student_free_slots(Weak::clone(&student)).first() {
       Some(slot) => slot
         .upgrade()
         .unwrap()
@@ -148,13 +243,18 @@ impl Timetable {
   pub fn get_student_free_slots(&self, student: Weak<Student>) -> Vec<Weak<Mutex<Slot>>> {
     let mut free_slots = Vec::<Weak<Mutex<Slot>>>::new();
     for slot in &self.slot_list {
-      if !slot.lock().unwrap().contains_student(Weak::clone(&student)) {
-        free_slots.push(Arc::downgrade(&slot));
+      if slot.lock().unwrap().is_free_for(Weak::clone(&student)) {
+        free_slots.push(Arc::downgrade(slot));
       }
     }
     free_slots
   }
+  // Number of places the student could join the subject, counting each group of a
+  // multi-slot subject once
   pub fn available_slots(&self, student: Weak<Student>, subject: Weak<Subject>) -> usize {
+    if subject.upgrade().unwrap().slot_demand > 1 {
+      return self.joinable_group_list(student, subject).len();
+    }
     let mut count = 0;
     for slot in self.get_student_free_slots(Weak::clone(&student)) {
       if slot
@@ -169,10 +269,299 @@ impl Timetable {
     }
     count
   }
+
+  // Tries to seat every displaced student in a surviving class of their subject, repeating
+  // until no further placements can be made. Returns the pairs that could not be placed.
+  pub fn place_displaced_students(
+    &mut self,
+    displaced_students: Vec<(Weak<Student>, Weak<Subject>)>,
+  ) -> Vec<(Weak<Student>, Weak<Subject>)> {
+    let mut remaining = displaced_students;
+    loop {
+      let remaining_count = remaining.len();
+      remaining.retain(|(student, subject)| {
+        self
+          .place_displaced_student(Weak::clone(student), Weak::clone(subject))
+          .is_err()
+      });
+      if remaining.is_empty() || remaining.len() == remaining_count {
+        return remaining;
+      }
+    }
+  }
+
+  pub fn place_displaced_student(
+    &mut self,
+    student: Weak<Student>,
+    subject: Weak<Subject>,
+  ) -> Result<()> {
+    // The student may already have been seated in the subject elsewhere
+    for slot in &self.slot_list {
+      if let Some(other) = slot.lock().unwrap().get_student_subject(Weak::clone(&student)) {
+        if other.ptr_eq(&subject) {
+          return Ok(());
+        }
+      }
+    }
+    if self.available_slots(Weak::clone(&student), Weak::clone(&subject)) != 0 {
+      return self.add_student_to_subject(student, subject);
+    }
+    if self.make_room_in_full_class(Weak::clone(&student), Weak::clone(&subject)) {
+      return Ok(());
+    }
+    if self.free_slot_for_subject(Weak::clone(&student), Weak::clone(&subject)) {
+      return Ok(());
+    }
+    Err(Error::Generic(
+      "No class available for displaced student".to_string(),
+    ))
+  }
+
+  // Moves another student out of a full class of the subject, into a parallel class in
+  // another slot, so that the student can take their seat
+  fn make_room_in_full_class(&mut self, student: Weak<Student>, subject: Weak<Subject>) -> bool {
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      let mut slot = slot.lock().unwrap();
+      if !slot.is_free_for(Weak::clone(&student)) || !slot.contains_subject(Weak::clone(&subject)) {
+        continue;
+      }
+      for class in &mut slot.class_list {
+        if class.removed || class.group.is_some() || !class.subject.ptr_eq(&subject) {
+          continue;
+        }
+        for other in class.student_list.clone() {
+          let Some(other_slot) = self.find_joinable_slot(Weak::clone(&other), Weak::clone(&subject), slot_index) else {
+            continue;
+          };
+          class.remove_student(Weak::clone(&other));
+          class.student_list.push(Weak::clone(&student));
+          self.slot_list[other_slot]
+            .lock()
+            .unwrap()
+            .add_student_to_subject(other, Weak::clone(&subject))
+            .unwrap();
+          return true;
+        }
+      }
+    }
+    false
+  }
+
+  // Moves the student out of a slot where the subject has space, into a parallel class of
+  // whatever they were taking there, so that the slot is free for the subject
+  fn free_slot_for_subject(&mut self, student: Weak<Student>, subject: Weak<Subject>) -> bool {
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      let mut slot = slot.lock().unwrap();
+      if !slot.contains_joinable_subject(Weak::clone(&subject))
+        || !slot.can_lose_student(Weak::clone(&student))
+      {
+        continue;
+      }
+      let other_subject = slot.get_student_subject(Weak::clone(&student)).unwrap();
+      let Some(other_slot) = self.find_joinable_slot(Weak::clone(&student), Weak::clone(&other_subject), slot_index) else {
+        continue;
+      };
+      slot.remove_student(Weak::clone(&student)).unwrap();
+      slot
+        .add_student_to_subject(Weak::clone(&student), subject)
+        .unwrap();
+      self.slot_list[other_slot]
+        .lock()
+        .unwrap()
+        .add_student_to_subject(student, other_subject)
+        .unwrap();
+      return true;
+    }
+    false
+  }
+
+  // Finds a slot, other than the one excluded, where the student is free and can join the subject
+  fn find_joinable_slot(
+    &self,
+    student: Weak<Student>,
+    subject: Weak<Subject>,
+    excluded_slot: usize,
+  ) -> Option<usize> {
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      if slot_index == excluded_slot {
+        continue;
+      }
+      let slot = slot.lock().unwrap();
+      if slot.is_free_for(Weak::clone(&student))
+        && slot.contains_joinable_subject(Weak::clone(&subject))
+      {
+        return Some(slot_index);
+      }
+    }
+    None
+  }
 }
 
 #[cfg(test)]
 mod tests {
+  use super::*;
+  use crate::{Department, StudentBuilder, SubjectBuilder};
+
+  fn new_student(id: &str) -> Arc<Student> {
+    Arc::new(
+      StudentBuilder::new()
+        .first_name("Person")
+        .last_name(id)
+        .id(id)
+        .build()
+        .unwrap(),
+    )
+  }
+
+  #[test]
+  fn test_get_free_slots() {
+    let department = Arc::new(Department {
+      name: "Maths".to_string(),
+      class_count: 10,
+      min_class_size: 1,
+      max_class_size: 30,
+    });
+    let subject = Arc::new(
+      SubjectBuilder::new()
+        .name("Calc")
+        .department(Arc::downgrade(&department))
+        .build()
+        .unwrap(),
+    );
+    let student = new_student("1");
+    let mut timetable = Timetable::new(3);
+    assert_eq!(
+      timetable
+        .get_student_free_slots(Arc::downgrade(&student))
+        .len(),
+      3
+    );
+    timetable
+      .add_student_to_subject(Arc::downgrade(&student), Arc::downgrade(&subject))
+      .unwrap();
+    assert_eq!(
+      timetable
+        .get_student_free_slots(Arc::downgrade(&student))
+        .len(),
+      2
+    );
+  }
+
   #[test]
-  fn test_get_free_slots() {}
+  fn test_place_displaced_student_by_moving_other_student() {
+    let department = Arc::new(Department {
+      name: "Maths".to_string(),
+      class_count: 10,
+      min_class_size: 1,
+      max_class_size: 2,
+    });
+    let calc = Arc::new(
+      SubjectBuilder::new()
+        .name("Calc")
+        .department(Arc::downgrade(&department))
+        .build()
+        .unwrap(),
+    );
+    let stats = Arc::new(
+      SubjectBuilder::new()
+        .name("Stats")
+        .department(Arc::downgrade(&department))
+        .build()
+        .unwrap(),
+    );
+    let (one, two, three, four) = (
+      new_student("1"),
+      new_student("2"),
+      new_student("3"),
+      new_student("4"),
+    );
+    let timetable = Timetable::new(2);
+    {
+      let mut slot = timetable.slot_list[0].lock().unwrap();
+      slot
+        .create_class_for_student(Arc::downgrade(&two), Arc::downgrade(&calc))
+        .unwrap();
+      slot
+        .add_student_to_subject(Arc::downgrade(&three), Arc::downgrade(&calc))
+        .unwrap();
+    }
+    {
+      let mut slot = timetable.slot_list[1].lock().unwrap();
+      slot
+        .create_class_for_student(Arc::downgrade(&four), Arc::downgrade(&calc))
+        .unwrap();
+      slot
+        .create_class_for_student(Arc::downgrade(&one), Arc::downgrade(&stats))
+        .unwrap();
+    }
+    let mut timetable = timetable;
+
+    // Calc is full in the only slot student one has free
+    let remaining = timetable
+      .place_displaced_students(vec![(Arc::downgrade(&one), Arc::downgrade(&calc))]);
+
+    assert!(remaining.is_empty());
+    let first_slot = timetable.slot_list[0].lock().unwrap();
+    assert!(first_slot.contains_student(Arc::downgrade(&one)));
+    assert_eq!(first_slot.class_list[0].student_list.len(), 2);
+  }
+
+  #[test]
+  fn test_place_displaced_student_by_freeing_slot() {
+    let department = Arc::new(Department {
+      name: "Maths".to_string(),
+      class_count: 10,
+      min_class_size: 1,
+      max_class_size: 3,
+    });
+    let calc = Arc::new(
+      SubjectBuilder::new()
+        .name("Calc")
+        .department(Arc::downgrade(&department))
+        .build()
+        .unwrap(),
+    );
+    let stats = Arc::new(
+      SubjectBuilder::new()
+        .name("Stats")
+        .department(Arc::downgrade(&department))
+        .build()
+        .unwrap(),
+    );
+    let (one, two, three) = (new_student("1"), new_student("2"), new_student("3"));
+    let mut timetable = Timetable::new(2);
+    {
+      let mut slot = timetable.slot_list[0].lock().unwrap();
+      slot
+        .create_class_for_student(Arc::downgrade(&two), Arc::downgrade(&calc))
+        .unwrap();
+      slot
+        .create_class_for_student(Arc::downgrade(&one), Arc::downgrade(&stats))
+        .unwrap();
+      slot
+        .add_student_to_subject(Arc::downgrade(&three), Arc::downgrade(&stats))
+        .unwrap();
+    }
+    timetable.slot_list[1]
+      .lock()
+      .unwrap()
+      .create_class_for_student(Arc::downgrade(&two), Arc::downgrade(&stats))
+      .unwrap();
+
+    // Student one can only take calc in the first slot, so stats has to move to the second
+    let remaining = timetable
+      .place_displaced_students(vec![(Arc::downgrade(&one), Arc::downgrade(&calc))]);
+
+    assert!(remaining.is_empty());
+    let first_slot = timetable.slot_list[0].lock().unwrap();
+    let second_slot = timetable.slot_list[1].lock().unwrap();
+    assert!(first_slot
+      .get_student_subject(Arc::downgrade(&one))
+      .unwrap()
+      .ptr_eq(&Arc::downgrade(&calc)));
+    assert!(second_slot
+      .get_student_subject(Arc::downgrade(&one))
+      .unwrap()
+      .ptr_eq(&Arc::downgrade(&stats)));
+  }
 }
@@ -0,0 +1,277 @@
+//! Subjects that take more than one slot, run as a group of classes with the same students
+
+use super::Timetable;
+use crate::{prelude::*, timetable::Class, Student, Subject};
+use std::sync::Weak;
+
+impl Timetable {
+  // Slots of each live group of the subject, along with whether every class in it has space
+  fn subject_group_list(&self, subject: &Weak<Subject>) -> Vec<(usize, Vec<usize>, bool)> {
+    let mut group_list: Vec<(usize, Vec<usize>, bool)> = Vec::new();
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      let slot = slot.lock().unwrap();
+      for (class_index, class) in slot.class_list.iter().enumerate() {
+        let Some(group) = class.group else {
+          continue;
+        };
+        if class.removed || !class.subject.ptr_eq(subject) {
+          continue;
+        }
+        let can_grow = slot.can_grow(class_index);
+        match group_list.iter_mut().find(|(other, _, _)| *other == group) {
+          Some((_, slot_list, has_space)) => {
+            slot_list.push(slot_index);
+            *has_space &= can_grow;
+          }
+          None => group_list.push((group, vec![slot_index], can_grow)),
+        }
+      }
+    }
+    group_list
+  }
+
+  // Groups of the subject with space that the student is free for in every slot
+  pub fn joinable_group_list(&self, student: Weak<Student>, subject: Weak<Subject>) -> Vec<usize> {
+    self
+      .subject_group_list(&subject)
+      .into_iter()
+      .filter(|(_, slot_list, has_space)| {
+        *has_space
+          && slot_list.iter().all(|&slot_index| {
+            self.slot_list[slot_index]
+              .lock()
+              .unwrap()
+              .is_free_for(Weak::clone(&student))
+          })
+      })
+      .map(|(group, _, _)| group)
+      .collect()
+  }
+
+  pub fn join_group(&mut self, student: Weak<Student>, group: usize) -> Result<()> {
+    let mut joined = false;
+    for slot in &self.slot_list {
+      for class in &mut slot.lock().unwrap().class_list {
+        if !class.removed && class.group == Some(group) {
+          class.student_list.push(Weak::clone(&student));
+          joined = true;
+        }
+      }
+    }
+    if !joined {
+      return Err(Error::Generic(format!("No group ({}) found", group)));
+    }
+    Ok(())
+  }
+
+  // Opens a new group of the subject in the first slots the student is free in and where one
+  // of the subject's departments has a room in every one of them
+  pub fn create_group_for_student(
+    &mut self,
+    student: Weak<Student>,
+    subject: Weak<Subject>,
+  ) -> Result<()> {
+    let slot_demand = subject.upgrade().unwrap().slot_demand;
+    let group = self.next_group();
+    for department in subject.upgrade().unwrap().department_list.clone() {
+      let slot_index_list: Vec<usize> = (0..self.slot_list.len())
+        .filter(|&slot_index| {
+          let slot = self.slot_list[slot_index].lock().unwrap();
+          slot.is_free_for(Weak::clone(&student)) && slot.can_open(&department, &subject)
+        })
+        .take(slot_demand)
+        .collect();
+      if slot_index_list.len() < slot_demand {
+        continue;
+      }
+      for slot_index in slot_index_list {
+        self.slot_list[slot_index]
+          .lock()
+          .unwrap()
+          .class_list
+          .push(Class {
+            group: Some(group),
+            ..Class::new(
+              Weak::clone(&subject),
+              Weak::clone(&department),
+              vec![Weak::clone(&student)],
+            )
+          });
+      }
+      return Ok(());
+    }
+    Err(Error::Generic(format!(
+      "Student does not have {} free slots with classrooms left for subject",
+      slot_demand
+    )))
+  }
+
+  fn next_group(&self) -> usize {
+    self
+      .slot_list
+      .iter()
+      .flat_map(|slot| {
+        slot
+          .lock()
+          .unwrap()
+          .class_list
+          .iter()
+          .filter_map(|class| class.group)
+          .collect::<Vec<_>>()
+      })
+      .max()
+      .map_or(0, |group| group + 1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{AnnealConfig, LocalSearchSolver, Schedule};
+  use std::sync::{Arc, Weak};
+
+  // Number of live physics classes each student is in
+  fn physics_class_count_list(high_school: &Schedule) -> Vec<usize> {
+    let physics = Arc::downgrade(&high_school.get_subject("Physics").unwrap());
+    high_school
+      .student_list
+      .iter()
+      .map(|student| {
+        high_school
+          .timetable
+          .slot_list
+          .iter()
+          .map(|slot| {
+            slot
+              .lock()
+              .unwrap()
+              .class_list
+              .iter()
+              .filter(|class| {
+                !class.removed
+                  && class.subject.ptr_eq(&physics)
+                  && class.contains(Arc::downgrade(student))
+              })
+              .count()
+          })
+          .sum()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_multi_slot_subject() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Science", 1, 2, 10).unwrap();
+    high_school.new_department("Maths", 1, 30, 10).unwrap();
+    high_school
+      .new_subject_with_slot_demand("Physics", vec!["Science"], 2)
+      .unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    for id in ["1", "2", "3"] {
+      high_school
+        .new_student("Person", id, id, vec!["Calculus", "Physics"])
+        .unwrap();
+    }
+    let report = high_school.sort().unwrap();
+    assert_eq!(report.placed_count(), 6);
+
+    let physics = Arc::downgrade(&high_school.get_subject("Physics").unwrap());
+    for student in &high_school.student_list {
+      // Every student takes physics in two slots, with the same classmates in both
+      let class_list: Vec<_> = high_school
+        .timetable
+        .slot_list
+        .iter()
+        .filter_map(|slot| {
+          slot
+            .lock()
+            .unwrap()
+            .class_list
+            .iter()
+            .find(|class| {
+              !class.removed
+                && class.subject.ptr_eq(&physics)
+                && class.contains(Arc::downgrade(student))
+            })
+            .cloned()
+        })
+        .collect();
+      assert_eq!(class_list.len(), 2);
+      assert_eq!(class_list[0].group, class_list[1].group);
+      assert!(class_list[0]
+        .student_list
+        .iter()
+        .zip(&class_list[1].student_list)
+        .all(|(one, two)| Weak::ptr_eq(one, two)));
+    }
+  }
+
+  #[test]
+  fn test_multi_slot_subject_needs_enough_slots() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Science", 1, 30, 10).unwrap();
+    high_school
+      .new_subject_with_slot_demand("Physics", vec!["Science"], 6)
+      .unwrap();
+    assert!(high_school
+      .new_student("Person", "One", "1", vec!["Physics"])
+      .is_err());
+  }
+
+  #[test]
+  fn test_full_multi_slot_subject_does_not_block_others() {
+    let mut high_school = Schedule::with_slot_count(5);
+    high_school.new_department("Science", 1, 1, 1).unwrap();
+    high_school.new_department("Maths", 1, 30, 1).unwrap();
+    high_school
+      .new_subject_with_slot_demand("Physics", vec!["Science"], 4)
+      .unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Physics"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Physics", "Calculus"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    // Physics has no room for a second group, but Calculus still fits
+    assert_eq!(report.student_report_list[1].placed.len(), 1);
+  }
+
+  #[test]
+  fn test_anneal_keeps_groups_together() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Science", 2, 4, 2).unwrap();
+    high_school.new_department("Maths", 2, 4, 2).unwrap();
+    high_school
+      .new_subject_with_slot_demand("Physics", vec!["Science"], 3)
+      .unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_subject("Statistics", vec!["Maths"])
+      .unwrap();
+    for index in 0..12 {
+      let id = index.to_string();
+      let subjects = match index % 3 {
+        0 => vec!["Physics", "Calculus"],
+        1 => vec!["Physics", "Statistics"],
+        _ => vec!["Calculus", "Statistics"],
+      };
+      high_school
+        .new_student("Person", &id, &id, subjects)
+        .unwrap();
+    }
+    high_school.seed = Some(7);
+    high_school
+      .sort_with(&mut LocalSearchSolver::new(AnnealConfig {
+        iteration_limit: Some(5000),
+        ..Default::default()
+      }))
+      .unwrap();
+
+    let count_list = physics_class_count_list(&high_school);
+    assert!(count_list.contains(&3));
+    assert!(count_list.iter().all(|&count| count == 0 || count == 3));
+  }
+}
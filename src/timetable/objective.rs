@@ -0,0 +1,245 @@
+//! Weighted scoring of a timetable, where a lower score is a better timetable
+
+use super::Timetable;
+use crate::{Department, Student, Subject};
+use serde::{Deserialize, Serialize};
+use std::{
+  fmt,
+  sync::{Arc, Weak},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectiveWeights {
+  // Per requested subject a student has no class for
+  pub unmet_request: f64,
+  // Per unmet request, for each of the student's choices ranked below it
+  pub unmet_rank: f64,
+  // Per class with fewer students than its department's minimum
+  pub undersized_class: f64,
+  // Per student of difference between the largest and smallest class of each subject
+  pub class_size_imbalance: f64,
+  // Per class run
+  pub opened_class: f64,
+  // Per class run by the named department, in place of opened_class
+  #[serde(default)]
+  pub department_opened_class_list: Vec<(String, f64)>,
+  // Per free slot a student has between their first and last class
+  pub slot_spread: f64,
+}
+
+impl Default for ObjectiveWeights {
+  fn default() -> Self {
+    Self {
+      unmet_request: 100.0,
+      unmet_rank: 10.0,
+      undersized_class: 50.0,
+      class_size_imbalance: 1.0,
+      opened_class: 2.0,
+      department_opened_class_list: Vec::new(),
+      slot_spread: 1.0,
+    }
+  }
+}
+
+impl ObjectiveWeights {
+  // What leaving the student without a class of the subject adds to the score
+  pub fn unmet_request_weight(&self, student: &Student, subject: &Weak<Subject>) -> f64 {
+    let rank_below = student
+      .rank(subject)
+      .map_or(0, |rank| student.subject_list.len() - rank - 1);
+    self.unmet_request + self.unmet_rank * rank_below as f64
+  }
+
+  pub fn opened_class_weight(&self, department: &Department) -> f64 {
+    self
+      .department_opened_class_list
+      .iter()
+      .find(|(name, _)| *name == department.name)
+      .map_or(self.opened_class, |(_, weight)| *weight)
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Score {
+  pub unmet_request_count: usize,
+  // Unmet requests weighted by how highly they were ranked, with a last choice counting 0
+  pub unmet_rank: usize,
+  pub undersized_class_count: usize,
+  pub class_size_imbalance: usize,
+  // Classes run by each department, in the order departments first appear in the timetable
+  pub department_class_count_list: Vec<(Weak<Department>, usize)>,
+  pub slot_spread: usize,
+  pub total: f64,
+}
+
+impl Score {
+  pub fn opened_class_count(&self) -> usize {
+    self
+      .department_class_count_list
+      .iter()
+      .map(|(_, count)| count)
+      .sum()
+  }
+}
+
+impl fmt::Display for Score {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Score {:.1} (unmet requests: {}, unmet rank: {}, undersized classes: {}, class size imbalance: {}, classes opened: {}, slot spread: {})",
+      self.total,
+      self.unmet_request_count,
+      self.unmet_rank,
+      self.undersized_class_count,
+      self.class_size_imbalance,
+      self.opened_class_count(),
+      self.slot_spread
+    )
+  }
+}
+
+impl Timetable {
+  // The unmet request and unmet rank part of the score
+  pub fn unmet_request_penalty(
+    &self,
+    student_list: &[Arc<Student>],
+    weights: &ObjectiveWeights,
+  ) -> f64 {
+    student_list
+      .iter()
+      .map(|student| {
+        self
+          .unsatisfied_requests(student)
+          .iter()
+          .map(|subject| weights.unmet_request_weight(student, subject))
+          .sum::<f64>()
+      })
+      .sum()
+  }
+
+  pub fn score(&self, student_list: &[Arc<Student>], weights: &ObjectiveWeights) -> Score {
+    let mut score = Score::default();
+    let mut subject_size_list: Vec<(Weak<Subject>, usize, usize)> = Vec::new();
+
+    for slot in &self.slot_list {
+      for class in slot.lock().unwrap().class_list.iter().filter(|x| !x.removed) {
+        let size = class.student_list.len();
+        if size < class.department.upgrade().unwrap().min_class_size {
+          score.undersized_class_count += 1;
+        }
+
+        match score
+          .department_class_count_list
+          .iter_mut()
+          .find(|(department, _)| department.ptr_eq(&class.department))
+        {
+          Some((_, count)) => *count += 1,
+          None => score
+            .department_class_count_list
+            .push((Weak::clone(&class.department), 1)),
+        }
+
+        match subject_size_list
+          .iter_mut()
+          .find(|(subject, _, _)| subject.ptr_eq(&class.subject))
+        {
+          Some((_, smallest, largest)) => {
+            *smallest = (*smallest).min(size);
+            *largest = (*largest).max(size);
+          }
+          None => subject_size_list.push((Weak::clone(&class.subject), size, size)),
+        }
+      }
+    }
+    score.class_size_imbalance = subject_size_list
+      .iter()
+      .map(|(_, smallest, largest)| largest - smallest)
+      .sum();
+
+    for student in student_list {
+      let unsatisfied = self.unsatisfied_requests(student);
+      score.unmet_request_count += unsatisfied.len();
+      score.unmet_rank += unsatisfied
+        .iter()
+        .filter_map(|subject| student.rank(subject))
+        .map(|rank| student.subject_list.len() - rank - 1)
+        .sum::<usize>();
+
+      let occupied: Vec<usize> = self
+        .slot_list
+        .iter()
+        .enumerate()
+        .filter(|(_, slot)| {
+          slot
+            .lock()
+            .unwrap()
+            .contains_student(Arc::downgrade(student))
+        })
+        .map(|(index, _)| index)
+        .collect();
+      if let (Some(first), Some(last)) = (occupied.first(), occupied.last()) {
+        score.slot_spread += last - first + 1 - occupied.len();
+      }
+    }
+
+    score.total = weights.unmet_request * score.unmet_request_count as f64
+      + weights.unmet_rank * score.unmet_rank as f64
+      + weights.undersized_class * score.undersized_class_count as f64
+      + weights.class_size_imbalance * score.class_size_imbalance as f64
+      + score
+        .department_class_count_list
+        .iter()
+        .map(|(department, count)| {
+          weights.opened_class_weight(&department.upgrade().unwrap()) * *count as f64
+        })
+        .sum::<f64>()
+      + weights.slot_spread * score.slot_spread as f64;
+    score
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Schedule;
+
+  #[test]
+  fn test_score() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 1, 30, 10).unwrap();
+    high_school.new_department("Art", 2, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Painting", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Painting"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Calculus"])
+      .unwrap();
+    high_school.sort().unwrap();
+
+    let score = high_school
+      .timetable
+      .score(&high_school.student_list, &ObjectiveWeights::default());
+
+    // Painting is removed for being too small, leaving one calculus class
+    assert_eq!(score.unmet_request_count, 1);
+    // Painting was the student's last choice
+    assert_eq!(score.unmet_rank, 0);
+    assert_eq!(score.undersized_class_count, 0);
+    assert_eq!(score.class_size_imbalance, 0);
+    assert_eq!(score.opened_class_count(), 1);
+    assert_eq!(score.slot_spread, 0);
+    assert_eq!(score.total, 102.0);
+
+    // Maths classes cost more to run than the default
+    let weights = ObjectiveWeights {
+      department_opened_class_list: vec![("Maths".to_string(), 5.0)],
+      ..Default::default()
+    };
+    let score = high_school
+      .timetable
+      .score(&high_school.student_list, &weights);
+    assert_eq!(score.total, 105.0);
+  }
+}
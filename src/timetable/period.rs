@@ -0,0 +1,281 @@
+//! Laying slots out over the days and periods of a school week
+
+use super::{Class, Timetable};
+use crate::{prelude::*, Student, Teacher};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Weak};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Period {
+  pub day: usize,
+  pub period: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeriodGrid {
+  // Days of the timetable cycle, such as "Mon" to "Fri"
+  pub day_list: Vec<String>,
+  pub period_count: usize,
+  // Periods of every day that are not taught in, such as recess and lunch
+  pub break_list: Vec<usize>,
+}
+
+impl PeriodGrid {
+  pub fn new(day_list: Vec<impl Into<String>>, period_count: usize) -> Self {
+    Self {
+      day_list: day_list.into_iter().map(|x| x.into()).collect(),
+      period_count,
+      break_list: Vec::new(),
+    }
+  }
+  pub fn is_break(&self, period: usize) -> bool {
+    self.break_list.contains(&period)
+  }
+  pub fn teaching_period_count(&self) -> usize {
+    self.day_list.len()
+      * (0..self.period_count)
+        .filter(|&x| !self.is_break(x))
+        .count()
+  }
+
+  // Gives each slot periods_per_slot periods on different days. Slots are spread evenly over
+  // the week, both in which days they meet and in which period of the day they land on.
+  pub fn map_slots(&self, slot_count: usize, periods_per_slot: usize) -> Result<PeriodMap> {
+    let day_count = self.day_list.len();
+    if periods_per_slot > day_count {
+      return Err(Error::Generic(format!(
+        "Cannot meet {} times in a {} day cycle without a slot meeting twice in a day",
+        periods_per_slot, day_count
+      )));
+    }
+    if slot_count * periods_per_slot > self.teaching_period_count() {
+      return Err(Error::Generic(format!(
+        "{} slots of {} periods do not fit in {} teaching periods",
+        slot_count,
+        periods_per_slot,
+        self.teaching_period_count()
+      )));
+    }
+
+    let mut taken = vec![vec![false; self.period_count]; day_count];
+    for day in &mut taken {
+      for (period, taken) in day.iter_mut().enumerate() {
+        *taken = self.is_break(period);
+      }
+    }
+    let mut day_load = vec![0; day_count];
+    let mut slot_period_list: Vec<Vec<Period>> = vec![Vec::new(); slot_count];
+
+    for round in 0..periods_per_slot {
+      for (slot, period_list) in slot_period_list.iter_mut().enumerate() {
+        // Where this meeting would fall if the slot's meetings were spaced out perfectly
+        let ideal_day = (slot + round * day_count / periods_per_slot) % day_count;
+        let Some(day) = (0..day_count)
+          .filter(|&day| period_list.iter().all(|x| x.day != day))
+          .filter(|&day| taken[day].iter().any(|x| !x))
+          .min_by_key(|&day| (day_load[day], (day + day_count - ideal_day) % day_count))
+        else {
+          return Err(Error::Generic(format!(
+            "Could not find a free day for slot {}",
+            slot
+          )));
+        };
+
+        // Rotate the slot through the day so it is not always first or last
+        let ideal_period = (slot + round) % self.period_count;
+        let period = (0..self.period_count)
+          .filter(|&period| !taken[day][period])
+          .min_by_key(|&period| {
+            (
+              period_list.iter().filter(|x| x.period == period).count(),
+              (period + self.period_count - ideal_period) % self.period_count,
+            )
+          })
+          .unwrap();
+
+        taken[day][period] = true;
+        day_load[day] += 1;
+        period_list.push(Period { day, period });
+      }
+    }
+
+    for period_list in &mut slot_period_list {
+      period_list.sort_by_key(|x| (x.day, x.period));
+    }
+    Ok(PeriodMap {
+      grid: self.clone(),
+      slot_period_list,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeriodMap {
+  pub grid: PeriodGrid,
+  // Periods each slot meets in, in week order
+  pub slot_period_list: Vec<Vec<Period>>,
+}
+
+impl PeriodMap {
+  pub fn get_slot(&self, period: Period) -> Option<usize> {
+    self
+      .slot_period_list
+      .iter()
+      .position(|period_list| period_list.contains(&period))
+  }
+
+  // Week of the classes that match the filter, at most one per slot
+  pub fn week_grid(&self, timetable: &Timetable, filter: impl Fn(&Class) -> bool) -> WeekGrid {
+    let mut cell_list = vec![vec![None; self.grid.period_count]; self.grid.day_list.len()];
+    for (slot_index, slot) in timetable.slot_list.iter().enumerate() {
+      let Some(period_list) = self.slot_period_list.get(slot_index) else {
+        continue;
+      };
+      let slot = slot.lock().unwrap();
+      let Some(class) = slot.class_list.iter().find(|x| !x.removed && filter(x)) else {
+        continue;
+      };
+      for period in period_list {
+        cell_list[period.day][period.period] = Some(class.clone());
+      }
+    }
+    WeekGrid {
+      grid: self.grid.clone(),
+      cell_list,
+    }
+  }
+
+  pub fn student_week(&self, timetable: &Timetable, student: Weak<Student>) -> WeekGrid {
+    self.week_grid(timetable, |class| class.contains(Weak::clone(&student)))
+  }
+
+  pub fn teacher_week(&self, timetable: &Timetable, teacher: Weak<Teacher>) -> WeekGrid {
+    self.week_grid(timetable, |class| {
+      class.teacher.as_ref().is_some_and(|x| x.ptr_eq(&teacher))
+    })
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct WeekGrid {
+  pub grid: PeriodGrid,
+  // Class in each period of each day, indexed by day then period
+  pub cell_list: Vec<Vec<Option<Class>>>,
+}
+
+impl WeekGrid {
+  pub fn get(&self, period: Period) -> Option<&Class> {
+    self.cell_list[period.day][period.period].as_ref()
+  }
+}
+
+impl fmt::Display for WeekGrid {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let cell_width = 16;
+    write!(f, "{:<8}", "")?;
+    for day in &self.grid.day_list {
+      write!(f, "{:<cell_width$}", day)?;
+    }
+    for period in 0..self.grid.period_count {
+      writeln!(f)?;
+      write!(f, "{:<8}", format!("P{}", period + 1))?;
+      for day in &self.cell_list {
+        let cell = if self.grid.is_break(period) {
+          "Break".to_string()
+        } else {
+          match &day[period] {
+            Some(class) => {
+              let mut name = class.subject.upgrade().unwrap().name.clone();
+              if let Some(room) = class.room.as_ref().and_then(|x| x.upgrade()) {
+                name = format!("{} ({})", name, room.name);
+              }
+              name
+            }
+            None => "-".to_string(),
+          }
+        };
+        write!(f, "{:<cell_width$}", cell)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Schedule;
+  use std::sync::Arc;
+
+  fn week() -> PeriodGrid {
+    PeriodGrid {
+      break_list: vec![2],
+      ..PeriodGrid::new(vec!["Mon", "Tue", "Wed", "Thu", "Fri"], 6)
+    }
+  }
+
+  #[test]
+  fn test_map_slots() {
+    let period_map = week().map_slots(6, 4).unwrap();
+
+    let mut seen = Vec::new();
+    for period_list in &period_map.slot_period_list {
+      assert_eq!(period_list.len(), 4);
+      for (index, period) in period_list.iter().enumerate() {
+        // Never twice on the same day, never in a break and never sharing a period
+        assert!(period_list[..index].iter().all(|x| x.day != period.day));
+        assert_ne!(period.period, 2);
+        assert!(!seen.contains(period));
+        seen.push(*period);
+      }
+    }
+    // Each day carries almost the same number of periods
+    let day_load: Vec<usize> = (0..5)
+      .map(|day| seen.iter().filter(|x| x.day == day).count())
+      .collect();
+    assert!(day_load.iter().max().unwrap() - day_load.iter().min().unwrap() <= 1);
+  }
+
+  #[test]
+  fn test_map_slots_full_grid() {
+    // Five slots meeting every day fill all five teaching periods of every day
+    let period_map = week().map_slots(5, 5).unwrap();
+    assert!(period_map.get_slot(Period { day: 4, period: 5 }).is_some());
+    assert_eq!(period_map.get_slot(Period { day: 0, period: 2 }), None);
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_map_slots_too_many_periods() {
+    week().map_slots(6, 6).unwrap();
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_map_slots_not_enough_periods() {
+    week().map_slots(7, 5).unwrap();
+  }
+
+  #[test]
+  fn test_student_week() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 1, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus"])
+      .unwrap();
+    high_school.sort().unwrap();
+    let period_map = week().map_slots(5, 3).unwrap();
+
+    let student = high_school.get_student("1").unwrap();
+    let week = period_map.student_week(&high_school.timetable, Arc::downgrade(&student));
+    let class_count = week
+      .cell_list
+      .iter()
+      .flatten()
+      .filter(|x| x.is_some())
+      .count();
+    assert_eq!(class_count, 3);
+    assert!(week.to_string().contains("Calculus"));
+  }
+}
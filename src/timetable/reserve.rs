@@ -0,0 +1,174 @@
+//! Seating students in their reserve subjects when their choices could not all be placed
+
+use super::Timetable;
+use crate::{Student, Subject};
+use std::sync::{Arc, Weak};
+
+impl Timetable {
+  // Whether the student is in a running class of the subject
+  fn takes_subject(&self, student: &Weak<Student>, subject: &Weak<Subject>) -> bool {
+    self.slot_list.iter().any(|slot| {
+      slot.lock().unwrap().class_list.iter().any(|class| {
+        !class.removed && class.subject.ptr_eq(subject) && class.contains(Weak::clone(student))
+      })
+    })
+  }
+
+  // Gives each student a reserve, in order of preference, for every choice they are missing.
+  // Reserves join classes already running where they have space, or else open a class where
+  // a department has room if may_open_classes is set. Opened classes that do not reach their
+  // department's minimum are closed again, and their students try their later reserves.
+  // Returns the number of reserves placed.
+  pub fn place_reserves(&mut self, student_list: &[Arc<Student>], may_open_classes: bool) -> usize {
+    let taken_before = self.reserve_count(student_list);
+    // Reserves whose class was closed, which are not opened again for the same student
+    let mut closed_list: Vec<(Weak<Student>, Weak<Subject>)> = Vec::new();
+    loop {
+      self.join_reserves(student_list, may_open_classes, &closed_list);
+      if !may_open_classes {
+        break;
+      }
+      let displaced_list = self.remove_small_classes();
+      let mut new_closed = false;
+      for (student, subject) in self.place_displaced_students(displaced_list) {
+        if !closed_list
+          .iter()
+          .any(|(x, y)| x.ptr_eq(&student) && y.ptr_eq(&subject))
+        {
+          closed_list.push((student, subject));
+          new_closed = true;
+        }
+      }
+      if !new_closed {
+        break;
+      }
+    }
+    self
+      .reserve_count(student_list)
+      .saturating_sub(taken_before)
+  }
+
+  fn reserve_count(&self, student_list: &[Arc<Student>]) -> usize {
+    student_list
+      .iter()
+      .map(|student| {
+        let weak_student = Arc::downgrade(student);
+        student
+          .reserve_list
+          .iter()
+          .filter(|reserve| self.takes_subject(&weak_student, reserve))
+          .count()
+      })
+      .sum()
+  }
+
+  fn join_reserves(
+    &mut self,
+    student_list: &[Arc<Student>],
+    may_open_classes: bool,
+    closed_list: &[(Weak<Student>, Weak<Subject>)],
+  ) {
+    for student in student_list {
+      let weak_student = Arc::downgrade(student);
+      let taken_count = student
+        .reserve_list
+        .iter()
+        .filter(|reserve| self.takes_subject(&weak_student, reserve))
+        .count();
+      let mut missing_count = self
+        .unsatisfied_requests(student)
+        .len()
+        .saturating_sub(taken_count);
+
+      for reserve in &student.reserve_list {
+        if missing_count == 0 {
+          break;
+        }
+        if self.takes_subject(&weak_student, reserve) {
+          continue;
+        }
+        let may_open = may_open_classes
+          && !closed_list
+            .iter()
+            .any(|(x, y)| x.ptr_eq(&weak_student) && y.ptr_eq(reserve));
+        let joined = if reserve.upgrade().unwrap().slot_demand > 1 {
+          match self
+            .joinable_group_list(Weak::clone(&weak_student), Weak::clone(reserve))
+            .first()
+          {
+            Some(&group) => self.join_group(Weak::clone(&weak_student), group).is_ok(),
+            None => {
+              may_open
+                && self
+                  .create_group_for_student(Weak::clone(&weak_student), Weak::clone(reserve))
+                  .is_ok()
+            }
+          }
+        } else {
+          self.slot_list.iter().any(|slot| {
+            let mut slot = slot.lock().unwrap();
+            slot.is_free_for(Weak::clone(&weak_student))
+              && slot.contains_joinable_subject(Weak::clone(reserve))
+              && slot
+                .add_student_to_subject(Weak::clone(&weak_student), Weak::clone(reserve))
+                .is_ok()
+          }) || (may_open
+            && self.slot_list.iter().any(|slot| {
+              let mut slot = slot.lock().unwrap();
+              slot.is_free_for(Weak::clone(&weak_student))
+                && slot
+                  .create_class_for_student(Weak::clone(&weak_student), Weak::clone(reserve))
+                  .is_ok()
+            }))
+        };
+        if joined {
+          missing_count -= 1;
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Schedule;
+
+  #[test]
+  fn test_place_reserves() {
+    let mut high_school = Schedule::with_slot_count(2);
+    high_school.new_department("Maths", 1, 1, 1).unwrap();
+    high_school.new_department("Art", 1, 30, 1).unwrap();
+    high_school.new_department("Languages", 2, 30, 1).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Latin", vec!["Languages"]).unwrap();
+    high_school
+      .new_subject("Statistics", vec!["Maths"])
+      .unwrap();
+    high_school.new_subject("Drawing", vec!["Art"]).unwrap();
+    high_school
+      .new_student("Person", "1", "1", vec!["Calculus", "Drawing"])
+      .unwrap();
+    // The calculus class has one seat. Nobody chose Latin or Statistics, so their classes are
+    // opened for the reserve, but a Latin class of one is too small to run.
+    high_school
+      .new_student_with_reserves(
+        "Person",
+        "2",
+        "2",
+        vec!["Calculus"],
+        vec!["Latin", "Statistics", "Drawing"],
+      )
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    let student_report = report.get_student_report("2").unwrap();
+    assert!(student_report.placed.is_empty());
+    assert_eq!(student_report.reserve_list.len(), 1);
+    assert_eq!(
+      student_report.reserve_list[0].upgrade().unwrap().name,
+      "Statistics"
+    );
+    assert_eq!(report.reserve_count(), 1);
+    assert!(report.to_string().contains("Reserves taken: 1"));
+  }
+}
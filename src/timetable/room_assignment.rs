@@ -0,0 +1,276 @@
+//! Assigning a room to every class in each slot of a timetable
+
+use super::{Class, Slot, Timetable};
+use crate::{Department, Subject};
+use std::sync::{Arc, Weak};
+
+impl Slot {
+  // Whether every running class could still have a room of its own that holds it after the
+  // class at leaving moves out, the class at grown takes one more student and a class of the
+  // given size arrives. Slots of a school without rooms always hold their classes.
+  pub fn rooms_hold_after(
+    &self,
+    leaving: Option<usize>,
+    grown: Option<usize>,
+    arriving: Option<(&Weak<Department>, &Weak<Subject>, usize)>,
+  ) -> bool {
+    if self.room_list.is_empty() {
+      return true;
+    }
+    let room_list: Vec<_> = self
+      .room_list
+      .iter()
+      .map(|x| x.upgrade().unwrap())
+      .collect();
+    let mut fits: Vec<Vec<usize>> = Vec::new();
+    for (index, class) in self.class_list.iter().enumerate() {
+      if (class.removed && grown != Some(index)) || leaving == Some(index) {
+        continue;
+      }
+      let size = class.student_list.len() + usize::from(grown == Some(index));
+      fits.push(
+        (0..room_list.len())
+          .filter(|&room| room_list[room].can_seat(&class.department, &class.subject, size))
+          .collect(),
+      );
+    }
+    if let Some((department, subject, size)) = arriving {
+      fits.push(
+        (0..room_list.len())
+          .filter(|&room| room_list[room].can_seat(department, subject, size))
+          .collect(),
+      );
+    }
+    if fits.len() > room_list.len() {
+      return false;
+    }
+    let mut room_class = vec![None; room_list.len()];
+    (0..fits.len()).all(|class| {
+      self.augment_room(
+        class,
+        &fits,
+        &mut room_class,
+        &mut vec![false; room_list.len()],
+      )
+    })
+  }
+
+  // Whether the class has a seat left, in a room it would still fit in
+  pub fn can_grow(&self, class_index: usize) -> bool {
+    !self.class_list[class_index].is_full() && self.rooms_hold_after(None, Some(class_index), None)
+  }
+
+  // Whether the department can open a class of the subject here, with a room that fits it
+  pub fn can_open(&self, department: &Weak<Department>, subject: &Weak<Subject>) -> bool {
+    !self.is_department_full(Weak::clone(department))
+      && self.rooms_hold_after(None, None, Some((department, subject, 1)))
+  }
+
+  // Tries to seat the class, moving classes to other rooms along the way if that frees up a
+  // room it fits in
+  fn augment_room(
+    &self,
+    class: usize,
+    fits: &[Vec<usize>],
+    room_class: &mut Vec<Option<usize>>,
+    visited: &mut [bool],
+  ) -> bool {
+    for &room in &fits[class] {
+      if visited[room] {
+        continue;
+      }
+      visited[room] = true;
+      let free = match room_class[room] {
+        None => true,
+        Some(other) => self.augment_room(other, fits, room_class, visited),
+      };
+      if free {
+        room_class[room] = Some(class);
+        return true;
+      }
+    }
+    false
+  }
+
+  // Gives every class that has not been removed a room that can hold it, with no room used
+  // twice. Returns the number of classes left without a room.
+  pub fn assign_rooms(&mut self) -> usize {
+    let room_list: Vec<_> = self
+      .room_list
+      .iter()
+      .map(|x| x.upgrade().unwrap())
+      .collect();
+    let fits: Vec<Vec<usize>> = self
+      .class_list
+      .iter()
+      .map(|class| {
+        if class.removed {
+          return Vec::new();
+        }
+        (0..room_list.len())
+          .filter(|&room| room_list[room].can_hold(class))
+          .collect()
+      })
+      .collect();
+
+    // Classes that fit the fewest rooms are the hardest to seat, so go first
+    let mut class_order: Vec<usize> = (0..self.class_list.len())
+      .filter(|&class| !self.class_list[class].removed)
+      .collect();
+    class_order.sort_by_key(|&class| fits[class].len());
+    let mut room_class = vec![None; room_list.len()];
+    for class in class_order {
+      self.augment_room(
+        class,
+        &fits,
+        &mut room_class,
+        &mut vec![false; room_list.len()],
+      );
+    }
+
+    for class in &mut self.class_list {
+      class.room = None;
+    }
+    for (room, class) in room_class.into_iter().enumerate() {
+      if let Some(class) = class {
+        self.class_list[class].room = Some(Arc::downgrade(&room_list[room]));
+      }
+    }
+    self
+      .class_list
+      .iter()
+      .filter(|class| !class.removed && class.room.is_none())
+      .count()
+  }
+}
+
+impl Timetable {
+  // Assigns rooms slot by slot. Returns the number of classes left without a room.
+  pub fn assign_rooms(&mut self) -> usize {
+    self
+      .slot_list
+      .iter()
+      .map(|slot| slot.lock().unwrap().assign_rooms())
+      .sum()
+  }
+
+  // Classes that have not been removed but have no room, with the index of their slot
+  pub fn unroomed_class_list(&self) -> Vec<(usize, Class)> {
+    let mut unroomed_class_list = Vec::new();
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      for class in &slot.lock().unwrap().class_list {
+        if !class.removed && class.room.is_none() {
+          unroomed_class_list.push((slot_index, class.clone()));
+        }
+      }
+    }
+    unroomed_class_list
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Schedule;
+
+  #[test]
+  fn test_assign_rooms() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Science", 1, 30, 10).unwrap();
+    high_school
+      .new_subject_with_features("Chemistry", vec!["Science"], vec!["lab"])
+      .unwrap();
+    high_school.new_subject("Biology", vec!["Science"]).unwrap();
+    // The lab is the only room chemistry fits in, so biology has to take the other room
+    high_school
+      .new_room("S1", 30, vec!["Science"], vec!["lab"])
+      .unwrap();
+    high_school
+      .new_room("S2", 30, vec!["Science"], Vec::<String>::new())
+      .unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Biology"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Chemistry"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    assert!(report.unroomed_class_list.is_empty());
+    for slot in &high_school.timetable.slot_list {
+      for class in &slot.lock().unwrap().class_list {
+        let room = class.room.as_ref().unwrap().upgrade().unwrap();
+        match class.subject.upgrade().unwrap().name.as_str() {
+          "Chemistry" => assert_eq!(room.name, "S1"),
+          _ => assert_eq!(room.name, "S2"),
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_rooms_limit_classes_per_slot() {
+    let mut high_school = Schedule::new();
+    // The department allows ten classes at once but only has one room
+    high_school.new_department("Maths", 1, 1, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school
+      .new_room("M1", 30, vec!["Maths"], Vec::<String>::new())
+      .unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus"])
+      .unwrap();
+    high_school
+      .new_student("Person", "Two", "2", vec!["Calculus"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    assert!(report.unroomed_class_list.is_empty());
+    for slot in &high_school.timetable.slot_list {
+      assert!(slot.lock().unwrap().class_list.len() <= 1);
+    }
+  }
+
+  #[test]
+  fn test_no_fitting_room_leaves_request_unplaced() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Science", 1, 30, 10).unwrap();
+    high_school
+      .new_subject_with_features("Chemistry", vec!["Science"], vec!["lab"])
+      .unwrap();
+    high_school
+      .new_room("S2", 30, vec!["Science"], Vec::<String>::new())
+      .unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Chemistry"])
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    // Chemistry needs a lab, so no class of it is opened
+    assert_eq!(report.placed_count(), 0);
+    assert!(report.unroomed_class_list.is_empty());
+  }
+
+  #[test]
+  fn test_rooms_limit_class_size() {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 1, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    // The department allows classes of thirty but its only room seats two
+    high_school
+      .new_room("M1", 2, vec!["Maths"], Vec::<String>::new())
+      .unwrap();
+    for id in 1..=3 {
+      high_school
+        .new_student("Person", "Number", id.to_string(), vec!["Calculus"])
+        .unwrap();
+    }
+    let report = high_school.sort().unwrap();
+
+    assert!(report.unroomed_class_list.is_empty());
+    for slot in &high_school.timetable.slot_list {
+      for class in &slot.lock().unwrap().class_list {
+        assert!(class.student_list.len() <= 2);
+      }
+    }
+  }
+}
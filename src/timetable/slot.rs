@@ -1,9 +1,15 @@
-use crate::{prelude::*, timetable::Class, Department, Student, Subject};
+use crate::{
+  prelude::*, room::rooms_fit, timetable::Class, Cohort, Department, Room, Student, Subject,
+};
 use std::sync::Weak;
 
 #[derive(Default, Debug)]
 pub struct Slot {
   pub class_list: Vec<Class>,
+  // Rooms free for classes in the slot, empty when the school has not declared its rooms
+  pub room_list: Vec<Weak<Room>>,
+  // Cohorts taught in the slot. Students outside of any cohort can be taught in every slot.
+  pub cohort_list: Vec<Weak<Cohort>>,
 }
 
 impl Slot {
@@ -12,27 +18,37 @@ impl Slot {
   }
   pub fn contains_student(&self, student: Weak<Student>) -> bool {
     for class in &self.class_list {
-      if class.contains(Weak::clone(&student)) {
+      if !class.removed && class.contains(Weak::clone(&student)) {
         return true;
       }
     }
     false
   }
+  pub fn is_open_to(&self, student: &Weak<Student>) -> bool {
+    match &student.upgrade().unwrap().cohort {
+      Some(cohort) => self.cohort_list.iter().any(|x| x.ptr_eq(cohort)),
+      None => true,
+    }
+  }
+  // Whether the student could be given a class in the slot
+  pub fn is_free_for(&self, student: Weak<Student>) -> bool {
+    self.is_open_to(&student) && !self.contains_student(student)
+  }
   pub fn contains_subject(&self, subject: Weak<Subject>) -> bool {
     for class in &self.class_list {
-      if class.subject.ptr_eq(&subject) {
+      if !class.removed && class.subject.ptr_eq(&subject) {
         return true;
       }
     }
     false
   }
+  // Grouped classes are only joined as a whole group, so are never joinable on their own
   pub fn contains_joinable_subject(&self, subject: Weak<Subject>) -> bool {
-    for class in &self.class_list {
-      if !class.subject.ptr_eq(&subject) {
+    for (index, class) in self.class_list.iter().enumerate() {
+      if class.removed || class.group.is_some() || !class.subject.ptr_eq(&subject) {
         continue;
       }
-      let max_class_size = class.department.upgrade().unwrap().max_class_size;
-      if class.student_list.len() >= max_class_size {
+      if !self.can_grow(index) {
         continue;
       }
       return true;
@@ -40,12 +56,51 @@ impl Slot {
     false
   }
 
-  pub fn remove_small_classes_and_get_displaced_students(&mut self) -> usize {
+  pub fn get_student_class_index(&self, student: Weak<Student>) -> Option<usize> {
     self
       .class_list
-      .iter_mut()
-      .map(|x| x.remove_if_too_small())
-      .sum::<usize>()
+      .iter()
+      .position(|class| !class.removed && class.contains(Weak::clone(&student)))
+  }
+
+  pub fn get_student_subject(&self, student: Weak<Student>) -> Option<Weak<Subject>> {
+    for class in &self.class_list {
+      if !class.removed && class.contains(Weak::clone(&student)) {
+        return Some(Weak::clone(&class.subject));
+      }
+    }
+    None
+  }
+
+  // Whether the student could leave their class in this slot without making it too small
+  pub fn can_lose_student(&self, student: Weak<Student>) -> bool {
+    for class in &self.class_list {
+      if !class.removed && class.contains(Weak::clone(&student)) {
+        return class.can_lose_student();
+      }
+    }
+    false
+  }
+
+  pub fn remove_student(&mut self, student: Weak<Student>) -> Result<Weak<Subject>> {
+    for class in &mut self.class_list {
+      if !class.removed && class.remove_student(Weak::clone(&student)) {
+        return Ok(Weak::clone(&class.subject));
+      }
+    }
+    Err(Error::Generic("Student is not in this slot".to_string()))
+  }
+
+  pub fn remove_small_classes_and_get_displaced_students(
+    &mut self,
+  ) -> Vec<(Weak<Student>, Weak<Subject>)> {
+    let mut displaced_students = Vec::new();
+    for class in &mut self.class_list {
+      for student in class.remove_if_too_small() {
+        displaced_students.push((student, Weak::clone(&class.subject)));
+      }
+    }
+    displaced_students
   }
 
   pub fn add_student_to_subject(
@@ -53,15 +108,17 @@ impl Slot {
     student: Weak<Student>,
     subject: Weak<Subject>,
   ) -> Result<()> {
-    for class in &mut self.class_list {
-      if !class.subject.ptr_eq(&subject) {
+    for index in 0..self.class_list.len() {
+      let class = &self.class_list[index];
+      if class.removed || class.group.is_some() || !class.subject.ptr_eq(&subject) {
         continue;
       }
-      let max_class_size = class.department.upgrade().unwrap().max_class_size;
-      if class.student_list.len() >= max_class_size {
+      if !self.can_grow(index) {
         continue;
       }
-      class.student_list.push(Weak::clone(&student));
+      self.class_list[index]
+        .student_list
+        .push(Weak::clone(&student));
       return Ok(());
     }
     Err(Error::Generic(
@@ -69,16 +126,59 @@ impl Slot {
     ))
   }
 
-  fn is_department_full(&self, department: Weak<Department>) -> bool {
-    let class_count = department.upgrade().unwrap().class_count;
+  pub fn department_class_count(&self, department: Weak<Department>) -> usize {
     let mut curr_class_count = 0;
 
     for class in &self.class_list {
-      if class.department.ptr_eq(&department) {
+      if !class.removed && class.department.ptr_eq(&department) {
         curr_class_count += 1;
       }
     }
-    curr_class_count >= class_count
+    curr_class_count
+  }
+
+  // Classes the department can run at once, counted from its rooms when the slot has any.
+  // Rooms it shares with other departments are left to the classes those already run here.
+  pub fn department_room_count(&self, department: Weak<Department>) -> usize {
+    if self.room_list.is_empty() {
+      return department.upgrade().unwrap().class_count;
+    }
+    // Classes of the other departments running here, with the department itself first
+    let mut department_list = vec![Weak::clone(&department)];
+    let mut class_count_list = vec![0];
+    for class in self.class_list.iter().filter(|x| !x.removed) {
+      match department_list
+        .iter()
+        .position(|x| x.ptr_eq(&class.department))
+      {
+        Some(0) => {}
+        Some(index) => class_count_list[index] += 1,
+        None => {
+          department_list.push(Weak::clone(&class.department));
+          class_count_list.push(1);
+        }
+      }
+    }
+    let room_department_list: Vec<Vec<usize>> = self
+      .room_list
+      .iter()
+      .map(|room| {
+        let room = room.upgrade().unwrap();
+        (0..department_list.len())
+          .filter(|&x| room.belongs_to(&department_list[x]))
+          .collect()
+      })
+      .collect();
+    loop {
+      class_count_list[0] += 1;
+      if !rooms_fit(&room_department_list, &class_count_list) {
+        return class_count_list[0] - 1;
+      }
+    }
+  }
+
+  pub fn is_department_full(&self, department: Weak<Department>) -> bool {
+    self.department_class_count(Weak::clone(&department)) >= self.department_room_count(department)
   }
 
   pub fn create_class_for_student(
@@ -86,18 +186,16 @@ impl Slot {
     student: Weak<Student>,
     subject: Weak<Subject>,
   ) -> Result<()> {
-    // Find the first empty department for the subject
+    // Find the first department for the subject with a room for the class
     for department in subject.upgrade().unwrap().department_list.clone() {
-      if self.is_department_full(Weak::clone(&department)) {
+      if !self.can_open(&department, &subject) {
         continue;
       }
-      // Department is not full
-      let new_class = Class {
-        subject: Weak::clone(&subject),
+      let new_class = Class::new(
+        Weak::clone(&subject),
         department,
-        student_list: vec![Weak::clone(&student)],
-        removed: false,
-      };
+        vec![Weak::clone(&student)],
+      );
       self.class_list.push(new_class);
       return Ok(());
     }
@@ -131,6 +229,57 @@ mod tests {
     assert!(slot.is_department_full(Arc::downgrade(&department_two)));
   }
 
+  #[test]
+  fn test_shared_room_is_department_full() {
+    let maths = Arc::new(Department {
+      name: "Maths".to_string(),
+      class_count: 30,
+      min_class_size: 1,
+      max_class_size: 30,
+    });
+    let science = Arc::new(Department {
+      name: "Science".to_string(),
+      class_count: 30,
+      min_class_size: 1,
+      max_class_size: 30,
+    });
+    let room = Arc::new(
+      crate::RoomBuilder::new()
+        .name("R1")
+        .capacity(30)
+        .department(Arc::downgrade(&maths))
+        .department(Arc::downgrade(&science))
+        .build()
+        .unwrap(),
+    );
+    let calc = Arc::new(
+      SubjectBuilder::new()
+        .name("Calc")
+        .department(Arc::downgrade(&maths))
+        .build()
+        .unwrap(),
+    );
+    let student = Arc::new(
+      StudentBuilder::new()
+        .first_name("Person")
+        .last_name("One")
+        .id("1")
+        .build()
+        .unwrap(),
+    );
+    let mut slot = Slot::new();
+    slot.room_list = vec![Arc::downgrade(&room)];
+    assert_eq!(slot.department_room_count(Arc::downgrade(&maths)), 1);
+    assert_eq!(slot.department_room_count(Arc::downgrade(&science)), 1);
+
+    // Once Maths takes the only room, Science has none left
+    slot
+      .create_class_for_student(Arc::downgrade(&student), Arc::downgrade(&calc))
+      .unwrap();
+    assert!(slot.is_department_full(Arc::downgrade(&maths)));
+    assert!(slot.is_department_full(Arc::downgrade(&science)));
+  }
+
   #[test]
   fn test_create_class_for_student() {
     let department = Arc::new(Department {
@@ -265,15 +414,15 @@ mod tests {
       .add_student_to_subject(Arc::downgrade(&student_two), Arc::downgrade(&subject))
       .unwrap();
     assert_eq!(slot.class_list.len(), 1);
-    assert_eq!(slot.class_list.get(0).unwrap().student_list.len(), 2);
+    assert_eq!(slot.class_list.first().unwrap().student_list.len(), 2);
     assert!(slot
       .class_list
-      .get(0)
+      .first()
       .unwrap()
       .contains(Arc::downgrade(&student_one)));
     assert!(slot
       .class_list
-      .get(0)
+      .first()
       .unwrap()
       .contains(Arc::downgrade(&student_two)));
   }
@@ -323,6 +472,45 @@ mod tests {
     assert!(slot.contains_joinable_subject(Arc::downgrade(&subject)));
   }
 
+  #[test]
+  fn test_remove_small_classes_and_get_displaced_students() {
+    let department = Arc::new(Department {
+      name: "Science".to_string(),
+      class_count: 10,
+      min_class_size: 2,
+      max_class_size: 35,
+    });
+    let subject = Arc::new(
+      SubjectBuilder::new()
+        .name("Physics")
+        .department(Arc::downgrade(&department))
+        .build()
+        .unwrap(),
+    );
+    let student_one = Arc::new(
+      StudentBuilder::new()
+        .first_name("Person")
+        .last_name("One")
+        .id("1")
+        .build()
+        .unwrap(),
+    );
+
+    let mut slot = Slot::new();
+    slot
+      .create_class_for_student(Arc::downgrade(&student_one), Arc::downgrade(&subject))
+      .unwrap();
+    let displaced_students = slot.remove_small_classes_and_get_displaced_students();
+    assert_eq!(displaced_students.len(), 1);
+    assert!(displaced_students[0].0.ptr_eq(&Arc::downgrade(&student_one)));
+    assert!(slot.class_list[0].removed);
+    assert!(!slot.contains_student(Arc::downgrade(&student_one)));
+    assert!(!slot.contains_joinable_subject(Arc::downgrade(&subject)));
+    assert!(slot
+      .remove_small_classes_and_get_displaced_students()
+      .is_empty());
+  }
+
   #[test]
   fn test_contains_subject_and_student() {
     let department = Arc::new(Department {
@@ -0,0 +1,231 @@
+//! Assigning a qualified teacher to every class in a timetable
+
+use super::{Class, Timetable};
+use crate::Teacher;
+use std::sync::{Arc, Weak};
+
+struct Assignment<'a> {
+  teacher_list: &'a [Arc<Teacher>],
+  // Slot of each class
+  class_slot: Vec<usize>,
+  // Teachers qualified for each class
+  qualified: Vec<Vec<usize>>,
+  class_teacher: Vec<Option<usize>>,
+  // Class each teacher teaches in each slot
+  teacher_slot: Vec<Vec<Option<usize>>>,
+  teacher_class_count: Vec<usize>,
+}
+
+impl Assignment<'_> {
+  fn assign(&mut self, class: usize, teacher: usize) {
+    self.class_teacher[class] = Some(teacher);
+    self.teacher_slot[teacher][self.class_slot[class]] = Some(class);
+    self.teacher_class_count[teacher] += 1;
+  }
+
+  fn unassign(&mut self, class: usize) {
+    let teacher = self.class_teacher[class].take().unwrap();
+    self.teacher_slot[teacher][self.class_slot[class]] = None;
+    self.teacher_class_count[teacher] -= 1;
+  }
+
+  // Finds a teacher for the class, moving other classes to other teachers along the way if
+  // that frees up a qualified teacher
+  fn augment(&mut self, class: usize, visited: &mut [bool]) -> bool {
+    let slot = self.class_slot[class];
+    for teacher in self.qualified[class].clone() {
+      if visited[teacher] {
+        continue;
+      }
+      visited[teacher] = true;
+
+      if let Some(other) = self.teacher_slot[teacher][slot] {
+        // The teacher is busy in this slot, so their class there needs someone else
+        self.unassign(other);
+        if self.augment(other, visited) {
+          self.assign(class, teacher);
+          return true;
+        }
+        self.assign(other, teacher);
+      } else if self.teacher_class_count[teacher] < self.teacher_list[teacher].max_class_count {
+        self.assign(class, teacher);
+        return true;
+      } else {
+        // The teacher has no classes to spare, so one of theirs needs someone else
+        let other_list: Vec<usize> = (0..self.class_teacher.len())
+          .filter(|&other| self.class_teacher[other] == Some(teacher))
+          .collect();
+        for other in other_list {
+          self.unassign(other);
+          if self.augment(other, visited) {
+            self.assign(class, teacher);
+            return true;
+          }
+          self.assign(other, teacher);
+        }
+      }
+    }
+    false
+  }
+}
+
+impl Timetable {
+  // Gives every class that has not been removed a qualified teacher who is free in its slot
+  // and below their maximum class count. Returns the number of classes left without a teacher.
+  pub fn assign_teachers(&mut self, teacher_list: &[Arc<Teacher>]) -> usize {
+    let mut class_index_list = Vec::new();
+    let mut assignment = Assignment {
+      teacher_list,
+      class_slot: Vec::new(),
+      qualified: Vec::new(),
+      class_teacher: Vec::new(),
+      teacher_slot: vec![vec![None; self.slot_list.len()]; teacher_list.len()],
+      teacher_class_count: vec![0; teacher_list.len()],
+    };
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      for (class_index, class) in slot.lock().unwrap().class_list.iter_mut().enumerate() {
+        class.teacher = None;
+        if class.removed {
+          continue;
+        }
+        class_index_list.push((slot_index, class_index));
+        assignment.class_slot.push(slot_index);
+        assignment.qualified.push(
+          (0..teacher_list.len())
+            .filter(|&teacher| teacher_list[teacher].can_teach(class))
+            .collect(),
+        );
+        assignment.class_teacher.push(None);
+      }
+    }
+
+    // Classes with the fewest qualified teachers are the hardest to staff, so go first
+    let mut class_order: Vec<usize> = (0..class_index_list.len()).collect();
+    class_order.sort_by_key(|&class| assignment.qualified[class].len());
+    for class in class_order {
+      assignment.augment(class, &mut vec![false; teacher_list.len()]);
+    }
+
+    let mut unassigned_count = 0;
+    for (class, (slot_index, class_index)) in class_index_list.into_iter().enumerate() {
+      let teacher = assignment.class_teacher[class].map(|x| Arc::downgrade(&teacher_list[x]));
+      if teacher.is_none() {
+        unassigned_count += 1;
+      }
+      self.slot_list[slot_index].lock().unwrap().class_list[class_index].teacher = teacher;
+    }
+    unassigned_count
+  }
+
+  // Classes that have not been removed but have no teacher, with the index of their slot
+  pub fn untaught_class_list(&self) -> Vec<(usize, Class)> {
+    let mut untaught_class_list = Vec::new();
+    for (slot_index, slot) in self.slot_list.iter().enumerate() {
+      for class in &slot.lock().unwrap().class_list {
+        if !class.removed && class.teacher.is_none() {
+          untaught_class_list.push((slot_index, class.clone()));
+        }
+      }
+    }
+    untaught_class_list
+  }
+
+  pub fn get_teacher_class_count(&self, teacher: Weak<Teacher>) -> usize {
+    self
+      .slot_list
+      .iter()
+      .map(|slot| {
+        slot
+          .lock()
+          .unwrap()
+          .class_list
+          .iter()
+          .filter(|class| {
+            !class.removed && class.teacher.as_ref().is_some_and(|x| x.ptr_eq(&teacher))
+          })
+          .count()
+      })
+      .sum()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Schedule;
+  use std::sync::Arc;
+
+  fn school() -> Schedule {
+    let mut high_school = Schedule::new();
+    high_school.new_department("Maths", 1, 30, 10).unwrap();
+    high_school.new_department("Science", 1, 30, 10).unwrap();
+    high_school.new_subject("Calculus", vec!["Maths"]).unwrap();
+    high_school.new_subject("Physics", vec!["Science"]).unwrap();
+    high_school
+      .new_student("Person", "One", "1", vec!["Calculus", "Physics"])
+      .unwrap();
+    high_school
+  }
+
+  #[test]
+  fn test_assign_teachers() {
+    let mut high_school = school();
+    // Teacher one could take either class, but teacher two can only take physics
+    high_school
+      .new_teacher(
+        "T1",
+        "Teacher One",
+        vec!["Calculus", "Physics"],
+        Vec::<String>::new(),
+        1,
+      )
+      .unwrap();
+    high_school
+      .new_teacher(
+        "T2",
+        "Teacher Two",
+        Vec::<String>::new(),
+        vec!["Science"],
+        1,
+      )
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    assert!(report.untaught_class_list.is_empty());
+    for slot in &high_school.timetable.slot_list {
+      for class in &slot.lock().unwrap().class_list {
+        let teacher = class.teacher.as_ref().unwrap().upgrade().unwrap();
+        let subject = class.subject.upgrade().unwrap();
+        match subject.name.as_str() {
+          "Calculus" => assert_eq!(teacher.id, "T1"),
+          _ => assert_eq!(teacher.id, "T2"),
+        }
+      }
+    }
+    let teacher = high_school.get_teacher("T1").unwrap();
+    assert_eq!(
+      high_school
+        .timetable
+        .get_teacher_class_count(Arc::downgrade(&teacher)),
+      1
+    );
+  }
+
+  #[test]
+  fn test_assign_teachers_reports_untaught_classes() {
+    let mut high_school = school();
+    high_school
+      .new_teacher(
+        "T1",
+        "Teacher One",
+        vec!["Calculus"],
+        Vec::<String>::new(),
+        5,
+      )
+      .unwrap();
+    let report = high_school.sort().unwrap();
+
+    assert_eq!(report.untaught_class_list.len(), 1);
+    let (_, class) = &report.untaught_class_list[0];
+    assert_eq!(class.subject.upgrade().unwrap().name, "Physics");
+  }
+}